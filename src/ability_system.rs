@@ -0,0 +1,178 @@
+use super::{
+    gamelog::GameLog,
+    particle_system::{ParticleBuilder, BEAM_MS},
+    Ability, Cloaked, Dash, Energy, Map, Name, Overclocked, Position, RunState, TileType, Viewshed,
+    WantsToUseItem,
+};
+use rltk::{Point, RGB};
+use specs::prelude::*;
+
+// Extra accuracy and damage while overclocked.
+pub const OVERCLOCK_BONUS: i32 = 3;
+
+// Pays for abilities and handles the parts of them that aren't item effects. Runs before
+// UseConsumableSystem, which applies damage, areas of effect and statuses as it does for items.
+pub struct AbilityUseSystem {}
+
+impl<'a> System<'a> for AbilityUseSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, Point>,
+        WriteExpect<'a, ParticleBuilder>,
+        ReadExpect<'a, Map>,
+        Entities<'a>,
+        ReadStorage<'a, WantsToUseItem>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, Ability>,
+        WriteStorage<'a, Energy>,
+        ReadStorage<'a, Dash>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Viewshed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            mut player_pos,
+            mut particle_builder,
+            map,
+            entities,
+            wants_to_use,
+            names,
+            mut abilities,
+            mut energy,
+            dashes,
+            mut positions,
+            mut viewsheds,
+        ) = data;
+
+        for (entity, useitem) in (&entities, &wants_to_use).join() {
+            let ability = match abilities.get_mut(useitem.item) {
+                None => continue,
+                Some(ability) => ability,
+            };
+            if let Some(energy) = energy.get_mut(entity) {
+                energy.energy -= ability.energy_cost;
+            }
+            ability.cooldown_remaining = ability.cooldown;
+            if entity == *player_entity {
+                gamelog.entries.push(format!(
+                    "You activate {}.",
+                    names.get(useitem.item).unwrap().name
+                ));
+            }
+
+            // dashing goes as far as it can towards the target without going through anything
+            if let (Some(_), Some(target)) = (dashes.get(useitem.item), useitem.target) {
+                let pos = positions.get_mut(entity).unwrap();
+                let from = Point::new(pos.x, pos.y);
+                let mut destination = from;
+                for point in rltk::line2d(rltk::LineAlg::Bresenham, from, target)
+                    .iter()
+                    .skip(1)
+                {
+                    let idx = map.xy_idx(point.x, point.y);
+                    if map.tiles[idx] == TileType::Wall || map.blocked[idx] {
+                        break;
+                    }
+                    destination = *point;
+                }
+                particle_builder.beam(
+                    from,
+                    destination,
+                    RGB::named(rltk::CYAN),
+                    rltk::to_cp437('»'),
+                );
+                particle_builder.request(
+                    from.x,
+                    from.y,
+                    RGB::named(rltk::CYAN),
+                    RGB::named(rltk::BLACK),
+                    rltk::to_cp437('░'),
+                    BEAM_MS,
+                );
+                pos.x = destination.x;
+                pos.y = destination.y;
+                if entity == *player_entity {
+                    *player_pos = destination;
+                }
+                if let Some(viewshed) = viewsheds.get_mut(entity) {
+                    viewshed.dirty = true;
+                }
+            }
+        }
+    }
+}
+
+// Once per turn: energy comes back, cooldowns and timed statuses run down.
+pub struct EnergySystem {}
+
+impl<'a> System<'a> for EnergySystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, RunState>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, Energy>,
+        WriteStorage<'a, Ability>,
+        WriteStorage<'a, Cloaked>,
+        WriteStorage<'a, Overclocked>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            runstate,
+            player_entity,
+            mut gamelog,
+            entities,
+            mut energy,
+            mut abilities,
+            mut cloaked,
+            mut overclocked,
+        ) = data;
+
+        if *runstate != RunState::MonsterTurn {
+            return;
+        }
+
+        for energy in (&mut energy).join() {
+            energy.energy = i32::min(energy.max_energy, energy.energy + energy.regen_rate);
+        }
+        for ability in (&mut abilities).join() {
+            ability.cooldown_remaining = i32::max(0, ability.cooldown_remaining - 1);
+        }
+
+        let mut expired: Vec<Entity> = Vec::new();
+        for (entity, cloak) in (&entities, &mut cloaked).join() {
+            cloak.turns -= 1;
+            if cloak.turns < 1 {
+                expired.push(entity);
+            }
+        }
+        for entity in expired.drain(..) {
+            cloaked.remove(entity);
+            if entity == *player_entity {
+                gamelog.entries.push("Your cloak flickers off.".to_string());
+            }
+        }
+
+        for (entity, overclock) in (&entities, &mut overclocked).join() {
+            overclock.turns -= 1;
+            if overclock.turns < 1 {
+                expired.push(entity);
+            }
+        }
+        for entity in expired.drain(..) {
+            overclocked.remove(entity);
+            if entity == *player_entity {
+                gamelog
+                    .entries
+                    .push("Your cyberware cools down.".to_string());
+            }
+        }
+    }
+}
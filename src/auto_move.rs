@@ -0,0 +1,233 @@
+use super::{
+    gamelog::GameLog, player::try_move_player, Item, Map, Monster, Position, RunState, TileType,
+};
+use rltk::{BaseMap, Point, Rltk};
+use specs::prelude::*;
+use std::collections::{HashSet, VecDeque};
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum Travel {
+    Explore,
+    To(usize),
+}
+
+// Walks the player one step a turn until they get where they're going or something turns up.
+// Exploring remembers the frontier tiles it has already stood on, because a tile next to a dark
+// corner stays next to it however long you stand there.
+#[derive(Default)]
+pub struct AutoMove {
+    pub travel: Option<Travel>,
+    visited: HashSet<usize>,
+    seen_items: HashSet<Entity>,
+}
+
+pub fn is_active(ecs: &World) -> bool {
+    ecs.fetch::<AutoMove>().travel.is_some()
+}
+
+pub fn start_explore(ecs: &mut World) -> RunState {
+    start(ecs, Travel::Explore)
+}
+
+pub fn travel_to_stairs(ecs: &mut World) -> RunState {
+    let stairs = {
+        let map = ecs.fetch::<Map>();
+        map.tiles
+            .iter()
+            .enumerate()
+            .find(|(idx, tile)| **tile == TileType::DownStairs && map.revealed_tiles[*idx])
+            .map(|(idx, _)| idx)
+    };
+    match stairs {
+        None => {
+            ecs.fetch_mut::<GameLog>()
+                .entries
+                .push("You haven't found a way down yet.".to_string());
+            RunState::AwaitingInput
+        }
+        Some(idx) => start(ecs, Travel::To(idx)),
+    }
+}
+
+// Clicking a tile you've seen walks you there.
+pub fn travel_to_mouse(ecs: &mut World, ctx: &Rltk) -> RunState {
+    let (x, y) = ctx.mouse_pos();
+    let idx = {
+        let map = ecs.fetch::<Map>();
+        if x < 1 || x > map.width - 1 || y < 1 || y > map.height - 1 {
+            return RunState::AwaitingInput;
+        }
+        let idx = map.xy_idx(x, y);
+        if !map.revealed_tiles[idx] || map.tiles[idx] == TileType::Wall {
+            return RunState::AwaitingInput;
+        }
+        idx
+    };
+    start(ecs, Travel::To(idx))
+}
+
+fn start(ecs: &mut World, travel: Travel) -> RunState {
+    if hostile_in_view(ecs) {
+        ecs.fetch_mut::<GameLog>()
+            .entries
+            .push("Not with enemies in view!".to_string());
+        return RunState::AwaitingInput;
+    }
+    let seen_items = visible_items(ecs);
+    let mut auto_move = ecs.fetch_mut::<AutoMove>();
+    auto_move.travel = Some(travel);
+    auto_move.visited.clear();
+    auto_move.seen_items = seen_items;
+    RunState::AwaitingInput
+}
+
+pub fn stop(ecs: &mut World) {
+    ecs.fetch_mut::<AutoMove>().travel = None;
+}
+
+// Takes the next step, or stops if a key was pressed, an enemy showed up, or there's nowhere
+// left to go.
+pub fn step(ecs: &mut World, ctx: &Rltk) -> RunState {
+    if ctx.key.is_some() {
+        stop(ecs);
+        return RunState::AwaitingInput;
+    }
+    if hostile_in_view(ecs) {
+        stop(ecs);
+        ecs.fetch_mut::<GameLog>()
+            .entries
+            .push("You stop, something hostile is in view.".to_string());
+        return RunState::AwaitingInput;
+    }
+
+    let travel = ecs.fetch::<AutoMove>().travel.unwrap();
+    if travel == Travel::Explore {
+        let items = visible_items(ecs);
+        let found = {
+            let mut auto_move = ecs.fetch_mut::<AutoMove>();
+            let found = items.difference(&auto_move.seen_items).count() > 0;
+            auto_move.seen_items.extend(items);
+            found
+        };
+        if found {
+            stop(ecs);
+            ecs.fetch_mut::<GameLog>()
+                .entries
+                .push("You spot something.".to_string());
+            return RunState::AwaitingInput;
+        }
+    }
+
+    let next = match travel {
+        Travel::Explore => next_explore_step(ecs),
+        Travel::To(target) => next_travel_step(ecs, target),
+    };
+    match next {
+        None => {
+            stop(ecs);
+            if travel == Travel::Explore {
+                ecs.fetch_mut::<GameLog>()
+                    .entries
+                    .push("There's nothing left to explore.".to_string());
+            }
+            RunState::AwaitingInput
+        }
+        Some(idx) => {
+            let (delta_x, delta_y) = {
+                let map = ecs.fetch::<Map>();
+                let player_pos = ecs.fetch::<Point>();
+                (
+                    idx as i32 % map.width - player_pos.x,
+                    idx as i32 / map.width - player_pos.y,
+                )
+            };
+            try_move_player(delta_x, delta_y, ecs)
+        }
+    }
+}
+
+fn hostile_in_view(ecs: &World) -> bool {
+    let map = ecs.fetch::<Map>();
+    let monsters = ecs.read_storage::<Monster>();
+    let positions = ecs.read_storage::<Position>();
+    (&monsters, &positions)
+        .join()
+        .any(|(_, pos)| map.visible_tiles[map.xy_idx(pos.x, pos.y)])
+}
+
+fn visible_items(ecs: &World) -> HashSet<Entity> {
+    let map = ecs.fetch::<Map>();
+    let entities = ecs.entities();
+    let items = ecs.read_storage::<Item>();
+    let positions = ecs.read_storage::<Position>();
+    (&entities, &items, &positions)
+        .join()
+        .filter(|(_, _, pos)| map.visible_tiles[map.xy_idx(pos.x, pos.y)])
+        .map(|(entity, _, _)| entity)
+        .collect()
+}
+
+// Breadth-first search over the tiles the player knows about, out to the nearest one that
+// borders the unknown. Returns the first step on the way there.
+fn next_explore_step(ecs: &mut World) -> Option<usize> {
+    let map = ecs.fetch::<Map>();
+    let player_pos = ecs.fetch::<Point>();
+    let mut auto_move = ecs.fetch_mut::<AutoMove>();
+    let start = map.xy_idx(player_pos.x, player_pos.y);
+    auto_move.visited.insert(start);
+
+    let mut came_from: Vec<Option<usize>> = vec![None; map.tiles.len()];
+    let mut queue: VecDeque<usize> = VecDeque::new();
+    came_from[start] = Some(start);
+    queue.push_back(start);
+    while let Some(idx) = queue.pop_front() {
+        if idx != start && !auto_move.visited.contains(&idx) && borders_unknown(&map, idx) {
+            // walk back to the step right after the start
+            let mut step = idx;
+            while came_from[step] != Some(start) {
+                step = came_from[step].unwrap();
+            }
+            return Some(step);
+        }
+        for (exit, _) in map.get_available_exits(idx).iter() {
+            if came_from[*exit].is_none() && map.revealed_tiles[*exit] {
+                came_from[*exit] = Some(idx);
+                queue.push_back(*exit);
+            }
+        }
+    }
+    None
+}
+
+fn borders_unknown(map: &Map, idx: usize) -> bool {
+    let x = idx as i32 % map.width;
+    let y = idx as i32 / map.width;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            // the outermost ring is never revealed, so it doesn't count
+            let (nx, ny) = (x + dx, y + dy);
+            if nx < 1 || nx > map.width - 2 || ny < 1 || ny > map.height - 2 {
+                continue;
+            }
+            if !map.revealed_tiles[map.xy_idx(nx, ny)] {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn next_travel_step(ecs: &World, target: usize) -> Option<usize> {
+    let map = ecs.fetch::<Map>();
+    let player_pos = ecs.fetch::<Point>();
+    let start = map.xy_idx(player_pos.x, player_pos.y);
+    if start == target {
+        return None;
+    }
+    let path = rltk::a_star_search(start, target, &*map);
+    if path.success && path.steps.len() > 1 {
+        Some(path.steps[1])
+    } else {
+        None
+    }
+}
@@ -0,0 +1,245 @@
+use super::{map_builders, Map, Position, TileType};
+use rltk::RandomNumberGenerator;
+use std::fs;
+use std::path::Path;
+
+pub const USAGE: &str = "\
+usage: mainframe [--seed N] [--show-mapgen]
+       mainframe --generate N [--builder NAME] [--seed N] [--out DIR]
+
+  --seed N          seed the random number generator, for reproducible runs
+  --show-mapgen     replay how the map was built before the game starts
+  --generate N      build N maps without opening a window, write them as ASCII and print stats
+  --builder NAME    generator to use with --generate (default: random)
+  --out DIR         where --generate writes its maps (default: ./mapgen)";
+
+pub struct Options {
+    pub seed: Option<u64>,
+    pub show_mapgen: bool,
+    pub generate: Option<usize>,
+    pub builder: Option<String>,
+    pub out_dir: String,
+}
+
+pub fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut options = Options {
+        seed: None,
+        show_mapgen: false,
+        generate: None,
+        builder: None,
+        out_dir: "./mapgen".to_string(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--show-mapgen" => options.show_mapgen = true,
+            "--seed" => options.seed = Some(parse_value(&arg, args.next())?),
+            "--generate" => options.generate = Some(parse_value(&arg, args.next())?),
+            "--builder" => {
+                let name = args.next().ok_or("--builder needs a value")?;
+                if name != "random" && !map_builders::BUILDER_NAMES.contains(&name.as_str()) {
+                    return Err(format!(
+                        "unknown builder {}, expected random or one of: {}",
+                        name,
+                        map_builders::BUILDER_NAMES.join(", ")
+                    ));
+                }
+                options.builder = Some(name);
+            }
+            "--out" => options.out_dir = args.next().ok_or("--out needs a value")?,
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+
+    Ok(options)
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or(format!("{} needs a value", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, got {}", flag, value))
+}
+
+// The seed to use for this run. Without --seed one is picked at random; callers report it, so an
+// interesting run can be reproduced.
+pub fn seed(options: &Options) -> u64 {
+    options
+        .seed
+        .unwrap_or_else(|| RandomNumberGenerator::new().next_u64())
+}
+
+struct MapStats {
+    rooms: usize,
+    walkable_percent: f32,
+    path_length: Option<usize>,
+    dead_ends: usize,
+}
+
+// Headless mode: builds `count` maps, writes each one to the output directory as ASCII and
+// prints per map and average stats.
+pub fn generate_maps(options: &Options, count: usize) -> std::io::Result<()> {
+    let seed = seed(options);
+    println!("seed: {}", seed);
+    let mut rng = RandomNumberGenerator::seeded(seed);
+    let builder_name = options.builder.as_deref().unwrap_or("random");
+    fs::create_dir_all(&options.out_dir)?;
+
+    let mut all_stats: Vec<MapStats> = Vec::new();
+    for i in 0..count {
+        let mut builder = match map_builders::builder_by_name(builder_name, 1) {
+            Some(builder) => builder,
+            None => map_builders::random_builder(1, &mut rng),
+        };
+        builder.build_map(&mut rng);
+        let map = builder.get_map();
+        let start = builder.get_starting_position();
+
+        let filename = Path::new(&options.out_dir).join(format!("map_{:03}.txt", i));
+        fs::write(&filename, map_to_ascii(&map, &start))?;
+
+        let stats = map_stats(&map, &start);
+        println!(
+            "{}  rooms: {}  walkable: {:.1}%  path to stairs: {}  dead ends: {}",
+            filename.display(),
+            stats.rooms,
+            stats.walkable_percent,
+            match stats.path_length {
+                None => "unreachable".to_string(),
+                Some(length) => length.to_string(),
+            },
+            stats.dead_ends
+        );
+        all_stats.push(stats);
+    }
+
+    if !all_stats.is_empty() {
+        let maps = all_stats.len() as f32;
+        let paths: Vec<usize> = all_stats.iter().filter_map(|s| s.path_length).collect();
+        println!(
+            "average of {} maps ({})  rooms: {:.1}  walkable: {:.1}%  path to stairs: {:.1}  dead ends: {:.1}",
+            all_stats.len(),
+            builder_name,
+            all_stats.iter().map(|s| s.rooms).sum::<usize>() as f32 / maps,
+            all_stats.iter().map(|s| s.walkable_percent).sum::<f32>() / maps,
+            paths.iter().sum::<usize>() as f32 / usize::max(1, paths.len()) as f32,
+            all_stats.iter().map(|s| s.dead_ends).sum::<usize>() as f32 / maps,
+        );
+    }
+
+    Ok(())
+}
+
+pub fn map_to_ascii(map: &Map, start: &Position) -> String {
+    let mut ascii = String::new();
+    for y in 0..map.height {
+        for x in 0..map.width {
+            if x == start.x && y == start.y {
+                ascii.push('@');
+                continue;
+            }
+            ascii.push(match map.tiles[map.xy_idx(x, y)] {
+                TileType::Wall => '#',
+                TileType::Floor => '.',
+                TileType::DownStairs => '>',
+            });
+        }
+        ascii.push('\n');
+    }
+    ascii
+}
+
+fn map_stats(map: &Map, start: &Position) -> MapStats {
+    let walkable = |x: i32, y: i32| {
+        x >= 0
+            && x < map.width
+            && y >= 0
+            && y < map.height
+            && map.tiles[map.xy_idx(x, y)] != TileType::Wall
+    };
+
+    let mut walkable_tiles = 0;
+    let mut dead_ends = 0;
+    let mut stairs: Option<usize> = None;
+    for y in 0..map.height {
+        for x in 0..map.width {
+            if !walkable(x, y) {
+                continue;
+            }
+            walkable_tiles += 1;
+            if map.tiles[map.xy_idx(x, y)] == TileType::DownStairs {
+                stairs = Some(map.xy_idx(x, y));
+            }
+
+            let exits = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                .iter()
+                .filter(|(dx, dy)| walkable(x + dx, y + dy))
+                .count();
+            if exits == 1 {
+                dead_ends += 1;
+            }
+        }
+    }
+
+    let path_length = stairs.and_then(|stairs| {
+        let path = rltk::a_star_search(map.xy_idx(start.x, start.y), stairs, map);
+        if path.success {
+            Some(path.steps.len() - 1)
+        } else {
+            None
+        }
+    });
+
+    MapStats {
+        rooms: map.rooms.len(),
+        walkable_percent: walkable_tiles as f32 * 100.0 / (map.width * map.height) as f32,
+        path_length,
+        dead_ends,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(name: &str, seed: u64) -> (Map, Position) {
+        let mut rng = RandomNumberGenerator::seeded(seed);
+        let mut builder = map_builders::builder_by_name(name, 1).unwrap();
+        builder.build_map(&mut rng);
+        (builder.get_map(), builder.get_starting_position())
+    }
+
+    #[test]
+    fn same_seed_builds_the_same_map() {
+        for name in map_builders::BUILDER_NAMES.iter() {
+            let (first, first_start) = build(name, 42);
+            let (second, second_start) = build(name, 42);
+            assert_eq!(
+                map_to_ascii(&first, &first_start),
+                map_to_ascii(&second, &second_start),
+                "{} isn't deterministic",
+                name
+            );
+
+            let first_stats = map_stats(&first, &first_start);
+            let second_stats = map_stats(&second, &second_start);
+            assert_eq!(first_stats.rooms, second_stats.rooms);
+            assert_eq!(first_stats.path_length, second_stats.path_length);
+            assert_eq!(first_stats.dead_ends, second_stats.dead_ends);
+        }
+    }
+
+    #[test]
+    fn every_builder_reports_rooms() {
+        for name in map_builders::BUILDER_NAMES.iter() {
+            let (map, _) = build(name, 7);
+            assert!(!map.rooms.is_empty(), "{} left the map without rooms", name);
+        }
+    }
+
+    #[test]
+    fn unknown_builder_is_rejected() {
+        let args = vec!["--builder".to_string(), "caves".to_string()];
+        assert!(parse_args(args.into_iter()).is_err());
+    }
+}
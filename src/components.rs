@@ -4,6 +4,7 @@ use specs::error::NoError;
 use specs::prelude::*;
 use specs::saveload::{ConvertSaveload, Marker};
 use specs_derive::*;
+use std::fmt;
 
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Position {
@@ -29,6 +30,12 @@ pub struct Viewshed {
     pub dirty: bool,
 }
 
+#[derive(Component, ConvertSaveload, Clone)]
+pub struct LightSource {
+    pub color: RGB,
+    pub range: i32,
+}
+
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Monster {}
 
@@ -46,6 +53,71 @@ pub struct CombatStats {
     pub hp: i32,
     pub defense: i32,
     pub power: i32,
+    pub accuracy: i32,
+    pub evasion: i32,
+    pub damage: rltk::DiceType,
+    pub damage_type: DamageType,
+}
+
+// An energy barrier that soaks up damage before Volts do. It starts recharging once its owner
+// has gone `recharge_delay` turns without taking a hit.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Shield {
+    pub max_shield: i32,
+    pub shield: i32,
+    pub recharge_delay: i32,
+    pub recharge_rate: i32,
+    pub turns_since_hit: i32,
+}
+
+// Raises the wearer's shield capacity and recharge rate while equipped.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct ShieldBonus {
+    pub capacity: i32,
+    pub recharge_rate: i32,
+}
+
+// Powers abilities; refills a little every turn.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Energy {
+    pub max_energy: i32,
+    pub energy: i32,
+    pub regen_rate: i32,
+}
+
+// A cyberware ability. It's used through WantsToUseItem like an item, so its effects are the same
+// components items use (InflictsDamage, AreaOfEffect, Ranged...).
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Ability {
+    pub owner: Entity,
+    pub hotkey: i32,
+    pub energy_cost: i32,
+    pub cooldown: i32,
+    pub cooldown_remaining: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Dash {}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct ProvidesCloak {
+    pub turns: i32,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct ProvidesOverclock {
+    pub turns: i32,
+}
+
+// Monsters can't see a cloaked entity; attacking drops the cloak.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Cloaked {
+    pub turns: i32,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Overclocked {
+    pub turns: i32,
 }
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
@@ -53,18 +125,49 @@ pub struct WantsToMelee {
     pub target: Entity,
 }
 
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum DamageType {
+    Kinetic,
+    Electric,
+    Thermal,
+    Emp,
+}
+
+impl fmt::Display for DamageType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DamageType::Kinetic => write!(f, "kinetic"),
+            DamageType::Electric => write!(f, "electric"),
+            DamageType::Thermal => write!(f, "thermal"),
+            DamageType::Emp => write!(f, "EMP"),
+        }
+    }
+}
+
+// Resistant entities take half damage of a type, vulnerable ones take double.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Resistances {
+    pub resistant: Vec<DamageType>,
+    pub vulnerable: Vec<DamageType>,
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct SufferDamage {
-    pub amount: Vec<i32>,
+    pub amount: Vec<(i32, DamageType)>,
 }
 
 impl SufferDamage {
-    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32) {
+    pub fn new_damage(
+        store: &mut WriteStorage<SufferDamage>,
+        victim: Entity,
+        amount: i32,
+        damage_type: DamageType,
+    ) {
         if let Some(suffering) = store.get_mut(victim) {
-            suffering.amount.push(amount);
+            suffering.amount.push((amount, damage_type));
         } else {
             let dmg = SufferDamage {
-                amount: vec![amount],
+                amount: vec![(amount, damage_type)],
             };
             store.insert(victim, dmg).expect("Unable to insert damage");
         }
@@ -77,6 +180,118 @@ pub struct Item {}
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Consumeable {}
 
+// The name an item goes by until the player works out what it is.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ObfuscatedName {
+    pub name: String,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ProvidesIdentification {}
+
+// Infected items backfire on their user, or won't come off once equipped. Nobody knows until it
+// shows itself or gets scanned.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Malware {
+    pub revealed: bool,
+}
+
+// Purges malware from everything its user carries or has installed.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ProvidesAntivirus {}
+
+// Money. On the player it's their wallet, on an item it's a credstick worth that much.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Credits {
+    pub amount: i32,
+}
+
+// What an item sells for. Vendors buy back at half.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Value {
+    pub credits: i32,
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub enum Loot {
+    Credits { max: i32 },
+    Item { name: String },
+}
+
+// Each drop is rolled separately, with a percent chance.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LootDrop {
+    pub chance: i32,
+    pub loot: Loot,
+}
+
+// What a monster leaves behind when it's destroyed.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct LootTable {
+    pub drops: Vec<LootDrop>,
+}
+
+// Trades with the player when bumped into. Its stock is whatever is in its backpack.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Vendor {}
+
+// Lockers, crates and husks. Holds items in its backpack and opens a transfer screen when bumped.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Container {}
+
+// Needs a keycard, or a hack that beats the difficulty, before it can be opened.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Locked {
+    pub difficulty: i32,
+}
+
+// Opens any lock, once.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Keycard {}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToUnlock {
+    pub container: Entity,
+}
+
+// In kilograms. A stack weighs this much per item.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Weight {
+    pub weight: f32,
+}
+
+// Carrying more than the owner's capacity. Counts monster turns so every so often the world gets
+// a free one.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Encumbered {
+    pub turns: i32,
+}
+
+// Quicker than normal. Every turn banks this much speed, and each time it adds up to a whole
+// turn the owner acts again before the monsters get to move.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Speed {
+    pub speed: i32,
+    pub banked: i32,
+}
+
+// Identical items carried as one backpack entry.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Stackable {
+    pub quantity: i32,
+}
+
+// Uses left in a device. Consumeable devices are used up at zero, the rest wait for a recharge.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Charges {
+    pub current: i32,
+    pub max: i32,
+}
+
+// Refills every charged device in the user's backpack.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ProvidesRecharge {}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct Ranged {
     pub range: i32,
@@ -85,6 +300,7 @@ pub struct Ranged {
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct InflictsDamage {
     pub damage: i32,
+    pub damage_type: DamageType,
 }
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
@@ -97,6 +313,99 @@ pub struct Disable {
     pub turns: i32,
 }
 
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum EquipmentSlot {
+    Weapon,
+    Shield,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Equippable {
+    pub slot: EquipmentSlot,
+}
+
+// Equipped items stay in the owner's backpack, this just marks which ones are in use.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Equipped {
+    pub owner: Entity,
+    pub slot: EquipmentSlot,
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum ImplantSlot {
+    Optics,
+    Arms,
+    Legs,
+    Spine,
+    Neural,
+}
+
+impl fmt::Display for ImplantSlot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImplantSlot::Optics => write!(f, "optics"),
+            ImplantSlot::Arms => write!(f, "arms"),
+            ImplantSlot::Legs => write!(f, "legs"),
+            ImplantSlot::Spine => write!(f, "spine"),
+            ImplantSlot::Neural => write!(f, "neural"),
+        }
+    }
+}
+
+// Cyberware that's installed for good. Each socket takes one implant and every implant eats into
+// the owner's humanity.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Implant {
+    pub slot: ImplantSlot,
+    pub humanity_cost: i32,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Installed {
+    pub owner: Entity,
+    pub slot: ImplantSlot,
+}
+
+// How much more cyberware a body can take before there's nobody left.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Humanity {
+    pub max_humanity: i32,
+    pub humanity: i32,
+}
+
+// Permanent stat changes applied once, when the implant goes in.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ImplantBonus {
+    pub sight: i32,
+    pub power: i32,
+    pub evasion: i32,
+    pub max_energy: i32,
+    pub speed: i32,
+}
+
+// The installed implant becomes an ability on the next free number key.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct GrantsAbility {
+    pub energy_cost: i32,
+    pub cooldown: i32,
+}
+
+// A reusable ranged weapon, fired from the weapon slot until the magazine runs dry.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Gun {
+    pub range: i32,
+    pub ammo: i32,
+    pub max_ammo: i32,
+    pub damage: rltk::DiceType,
+    pub damage_type: DamageType,
+}
+
+// Loose rounds; pickups merge into the stack already in the backpack.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Ammo {
+    pub rounds: i32,
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct ProvidesHealing {
     pub heal_amount: i32,
@@ -124,6 +433,26 @@ pub struct WantsToDropItem {
     pub item: Entity,
 }
 
+#[derive(Component, Debug, ConvertSaveload)]
+pub struct WantsToThrowItem {
+    pub item: Entity,
+    pub target: rltk::Point,
+}
+
+#[derive(Component, Debug, ConvertSaveload)]
+pub struct WantsToShoot {
+    pub target: rltk::Point,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct WantsToReload {}
+
+// Short-lived visual effects; never saved, they're gone long before anyone could load them.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct ParticleLifetime {
+    pub lifetime_ms: f32,
+}
+
 // Serialization helper code. We need to implement ConvertSaveload for each type that contains an
 // Entity.
 
@@ -133,4 +462,5 @@ pub struct SerializeMe;
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct SerializationHelper {
     pub map: super::map::Map,
+    pub knowledge: super::identification_system::ItemKnowledge,
 }
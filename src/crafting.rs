@@ -0,0 +1,172 @@
+use super::{
+    gamelog::GameLog, identification_system::ItemKnowledge, inventory_system, spawner, Equipped,
+    InBackpack, Malware, Name, Position, Stackable,
+};
+use serde::Deserialize;
+use specs::prelude::*;
+
+#[derive(Deserialize, Clone)]
+pub struct Ingredient {
+    pub name: String,
+    pub quantity: i32,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Recipe {
+    pub output: String,
+    pub inputs: Vec<Ingredient>,
+}
+
+// Everything the player knows how to build, read from raws/recipes.json.
+#[derive(Default, Clone)]
+pub struct Recipes {
+    pub recipes: Vec<Recipe>,
+}
+
+pub fn load_recipes() -> Recipes {
+    let raw = include_str!("../raws/recipes.json");
+    let recipes: Vec<Recipe> = serde_json::from_str(raw).expect("Unable to parse recipes");
+    Recipes { recipes }
+}
+
+// How many of a thing the player has loose in their backpack. Worn gear doesn't count.
+pub fn count_in_backpack(ecs: &World, name: &str) -> i32 {
+    let player_entity = *ecs.fetch::<Entity>();
+    let entities = ecs.entities();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let names = ecs.read_storage::<Name>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let stackable = ecs.read_storage::<Stackable>();
+    (&entities, &backpack, &names)
+        .join()
+        .filter(|(item, pack, item_name)| {
+            pack.owner == player_entity && item_name.name == name && equipped.get(*item).is_none()
+        })
+        .map(|(item, _, _)| stackable.get(item).map_or(1, |s| s.quantity))
+        .sum()
+}
+
+pub fn can_craft(ecs: &World, recipe: &Recipe) -> bool {
+    recipe
+        .inputs
+        .iter()
+        .all(|input| count_in_backpack(ecs, &input.name) >= input.quantity)
+}
+
+// Takes this many of a thing out of the player's backpack, stack by stack.
+fn consume(ecs: &mut World, name: &str, quantity: i32) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut remaining = quantity;
+    let mut to_delete: Vec<Entity> = Vec::new();
+    {
+        let entities = ecs.entities();
+        let backpack = ecs.read_storage::<InBackpack>();
+        let names = ecs.read_storage::<Name>();
+        let equipped = ecs.read_storage::<Equipped>();
+        let mut stackable = ecs.write_storage::<Stackable>();
+        for (item, pack, item_name) in (&entities, &backpack, &names).join() {
+            if remaining == 0 {
+                break;
+            }
+            if pack.owner != player_entity || item_name.name != name || equipped.get(item).is_some()
+            {
+                continue;
+            }
+            match stackable.get_mut(item) {
+                Some(stack) if stack.quantity > remaining => {
+                    stack.quantity -= remaining;
+                    remaining = 0;
+                }
+                Some(stack) => {
+                    remaining -= stack.quantity;
+                    to_delete.push(item);
+                }
+                None => {
+                    remaining -= 1;
+                    to_delete.push(item);
+                }
+            }
+        }
+    }
+    for item in to_delete {
+        ecs.delete_entity(item).expect("Delete failed");
+    }
+}
+
+// Uses up a recipe's inputs and puts what it makes in the player's backpack, or at their feet if
+// there's no room. The output is made first, so a recipe for something that doesn't exist costs
+// the player nothing.
+pub fn craft(ecs: &mut World, recipe: &Recipe) {
+    if !can_craft(ecs, recipe) {
+        ecs.fetch_mut::<GameLog>()
+            .entries
+            .push(format!("You don't have the parts for a {}.", recipe.output));
+        return;
+    }
+
+    let player_entity = *ecs.fetch::<Entity>();
+    let (x, y) = {
+        let positions = ecs.read_storage::<Position>();
+        let pos = positions.get(player_entity).unwrap();
+        (pos.x, pos.y)
+    };
+    let item = match spawner::named_item(ecs, x, y, &recipe.output) {
+        Some(item) => item,
+        None => {
+            rltk::console::log(format!("No such item to craft: {}", recipe.output));
+            return;
+        }
+    };
+    for input in recipe.inputs.iter() {
+        consume(ecs, &input.name, input.quantity);
+    }
+    // you know what you just built
+    ecs.fetch_mut::<ItemKnowledge>()
+        .known
+        .insert(recipe.output.clone());
+
+    // fresh from clean parts
+    ecs.write_storage::<Malware>().remove(item);
+    match inventory_system::move_to_backpack(ecs, item, player_entity) {
+        Ok(()) => {
+            ecs.write_storage::<Position>().remove(item);
+            ecs.fetch_mut::<GameLog>()
+                .entries
+                .push(format!("You put together a {}.", recipe.output));
+        }
+        Err(reason) => {
+            let mut gamelog = ecs.fetch_mut::<GameLog>();
+            gamelog
+                .entries
+                .push(format!("You put together a {}.", recipe.output));
+            gamelog.entries.push(reason);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_world;
+
+    // A typo in raws/recipes.json should fail here, not in the middle of a run.
+    #[test]
+    fn every_recipe_names_real_items() {
+        let mut ecs = test_world(1);
+
+        for recipe in load_recipes().recipes.iter() {
+            assert!(
+                spawner::named_item(&mut ecs, 0, 0, &recipe.output).is_some(),
+                "no such item: {}",
+                recipe.output
+            );
+            for input in recipe.inputs.iter() {
+                assert!(
+                    spawner::named_item(&mut ecs, 0, 0, &input.name).is_some(),
+                    "no such item: {}",
+                    input.name
+                );
+            }
+        }
+    }
+}
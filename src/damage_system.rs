@@ -1,20 +1,57 @@
-use super::{gamelog::GameLog, CombatStats, Name, Player, SufferDamage};
-use rltk::console;
+use super::{
+    gamelog::GameLog, spawner, CombatStats, Loot, LootTable, Name, Player, Position, Resistances,
+    Shield, SufferDamage,
+};
+use rltk::{console, RandomNumberGenerator};
 use specs::prelude::*;
 
 pub struct DamageSystem {}
 
 impl<'a> System<'a> for DamageSystem {
     type SystemData = (
+        Entities<'a>,
+        WriteExpect<'a, GameLog>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Resistances>,
         WriteStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
+        WriteStorage<'a, Shield>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut stats, mut damage) = data;
+        let (entities, mut log, names, resistances, mut stats, mut damage, mut shields) = data;
 
-        for (mut stats, damage) in (&mut stats, &damage).join() {
-            stats.hp -= damage.amount.iter().sum::<i32>();
+        for (entity, stats, damage) in (&entities, &mut stats, &damage).join() {
+            for (amount, damage_type) in damage.amount.iter() {
+                let mut amount = *amount;
+                // vulnerabilities double the hit, resistances halve it
+                if let Some(resist) = resistances.get(entity) {
+                    let name = names.get(entity).map_or("It", |n| n.name.as_str());
+                    if resist.vulnerable.contains(damage_type) {
+                        amount *= 2;
+                        log.entries
+                            .push(format!("{} is vulnerable to {} damage!", name, damage_type));
+                    } else if resist.resistant.contains(damage_type) {
+                        amount = i32::max(1, amount / 2);
+                        log.entries
+                            .push(format!("{} resists the {} damage.", name, damage_type));
+                    }
+                }
+                // the shield takes what it can, the rest goes through to volts
+                if let Some(shield) = shields.get_mut(entity) {
+                    shield.turns_since_hit = 0;
+                    if shield.shield > 0 {
+                        let absorbed = i32::min(shield.shield, amount);
+                        shield.shield -= absorbed;
+                        amount -= absorbed;
+                        if shield.shield == 0 {
+                            let name = names.get(entity).map_or("It", |n| n.name.as_str());
+                            log.entries.push(format!("{}'s shield collapses!", name));
+                        }
+                    }
+                }
+                stats.hp -= amount;
+            }
         }
 
         damage.clear();
@@ -47,6 +84,38 @@ pub fn delete_the_dead(ecs: &mut World) {
         }
     }
 
+    // roll each victim's loot table and leave whatever comes up where it fell
+    let mut drops: Vec<(i32, i32, Loot)> = Vec::new();
+    {
+        let positions = ecs.read_storage::<Position>();
+        let loot_tables = ecs.read_storage::<LootTable>();
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        for victim in dead.iter() {
+            if let (Some(pos), Some(table)) = (positions.get(*victim), loot_tables.get(*victim)) {
+                for drop in table.drops.iter() {
+                    if rng.roll_dice(1, 100) <= drop.chance {
+                        drops.push((pos.x, pos.y, drop.loot.clone()));
+                    }
+                }
+            }
+        }
+    }
+    for (x, y, loot) in drops {
+        match loot {
+            Loot::Credits { max } => {
+                let amount = ecs
+                    .write_resource::<RandomNumberGenerator>()
+                    .range(1, max + 1);
+                spawner::credstick(ecs, x, y, amount);
+            }
+            Loot::Item { name } => {
+                if spawner::named_item(ecs, x, y, &name).is_none() {
+                    console::log(format!("No such item to drop: {}", name));
+                }
+            }
+        }
+    }
+
     for victim in dead {
         ecs.delete_entity(victim).expect("Unable to delete");
     }
@@ -0,0 +1,102 @@
+use super::{gamelog::GameLog, CombatStats, Encumbered, InBackpack, RunState, Stackable, Weight};
+use specs::prelude::*;
+use specs::storage::MaskedStorage;
+use std::ops::Deref;
+
+// Past this multiple of capacity nothing more can be picked up at all.
+pub const HARD_CAP: f32 = 1.5;
+const EVASION_PENALTY: i32 = 3;
+// Every this many monster turns, an encumbered entity loses its turn to them.
+pub const SLOW_EVERY: i32 = 3;
+
+// Stronger bodies carry more.
+pub fn carry_capacity(stats: &CombatStats) -> f32 {
+    10.0 + stats.power as f32 * 2.0
+}
+
+pub fn carried_weight<B, W, S>(
+    backpack: &Storage<InBackpack, B>,
+    weights: &Storage<Weight, W>,
+    stackable: &Storage<Stackable, S>,
+    owner: Entity,
+) -> f32
+where
+    B: Deref<Target = MaskedStorage<InBackpack>>,
+    W: Deref<Target = MaskedStorage<Weight>>,
+    S: Deref<Target = MaskedStorage<Stackable>>,
+{
+    (backpack, weights, stackable.maybe())
+        .join()
+        .filter(|(pack, _, _)| pack.owner == owner)
+        .map(|(_, weight, stack)| weight.weight * stack.map_or(1, |s| s.quantity) as f32)
+        .sum()
+}
+
+pub fn effective_evasion(stats: &CombatStats, encumbered: Option<&Encumbered>) -> i32 {
+    match encumbered {
+        Some(_) => stats.evasion - EVASION_PENALTY,
+        None => stats.evasion,
+    }
+}
+
+// Works out who's carrying too much and counts their turns.
+pub struct EncumbranceSystem {}
+
+impl<'a> System<'a> for EncumbranceSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, RunState>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, Weight>,
+        ReadStorage<'a, Stackable>,
+        WriteStorage<'a, Encumbered>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            runstate,
+            mut gamelog,
+            entities,
+            combat_stats,
+            backpack,
+            weights,
+            stackable,
+            mut encumbered,
+        ) = data;
+
+        for (entity, stats) in (&entities, &combat_stats).join() {
+            let load = carried_weight(&backpack, &weights, &stackable, entity);
+            let overloaded = load > carry_capacity(stats);
+            let was_overloaded = encumbered.get(entity).is_some();
+
+            if overloaded && !was_overloaded {
+                encumbered
+                    .insert(entity, Encumbered { turns: 0 })
+                    .expect("Unable to insert encumbrance");
+                if entity == *player_entity {
+                    gamelog
+                        .entries
+                        .push("You are overloaded and slow down.".to_string());
+                }
+            } else if !overloaded && was_overloaded {
+                encumbered.remove(entity);
+                if entity == *player_entity {
+                    gamelog
+                        .entries
+                        .push("Your load is manageable again.".to_string());
+                }
+            }
+
+            if *runstate == RunState::MonsterTurn {
+                if let Some(encumbered) = encumbered.get_mut(entity) {
+                    encumbered.turns += 1;
+                }
+            }
+        }
+    }
+}
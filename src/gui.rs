@@ -1,6 +1,9 @@
 use super::{
-    gamelog::GameLog, CombatStats, InBackpack, Map, Name, Player, Position, RunState, State,
-    Viewshed,
+    crafting, encumbrance_system, gamelog::GameLog, identification_system, player,
+    ranged_combat_system::equipped_weapon, shield_system, Ability, Ammo, Charges, CombatStats,
+    Credits, Energy, Equipped, Gun, Humanity, Implant, InBackpack, Item, Malware, Map, Monster,
+    Name, Player, Position, RunState, Shield, ShieldBonus, Stackable, State, TileType, Value,
+    Viewshed, Weight,
 };
 use rltk::{Point, Rltk, VirtualKeyCode, RGB};
 use specs::prelude::*;
@@ -18,11 +21,24 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     let combat_stats = ecs.read_storage::<CombatStats>();
     let players = ecs.read_storage::<Player>();
 
+    let player_entity = ecs.fetch::<Entity>();
+    if let Some(wallet) = ecs.read_storage::<Credits>().get(*player_entity) {
+        let wallet_text = format!("${}", wallet.amount);
+        ctx.print_color(
+            2,
+            43,
+            RGB::named(rltk::GOLD),
+            RGB::named(rltk::BLACK),
+            &wallet_text,
+        );
+    }
+
+    // volts, shield and energy share the top edge, so the log keeps its five lines
     for (_player, stats) in (&players, &combat_stats).join() {
         let health = format!("Volts: {}/{}", stats.hp, stats.max_hp);
 
         ctx.print_color(
-            12,
+            10,
             43,
             RGB::named(rltk::DEEP_PINK),
             RGB::named(rltk::BLACK),
@@ -30,9 +46,9 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         );
 
         ctx.draw_bar_horizontal(
-            28,
+            25,
             43,
-            51,
+            12,
             stats.hp,
             stats.max_hp,
             RGB::named(rltk::DARK_TURQUOISE),
@@ -40,13 +56,62 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         );
     }
 
+    let shields = ecs.read_storage::<Shield>();
+    if let Some(shield) = shields.get(*player_entity) {
+        let (capacity, _) = shield_system::shield_capacity(
+            shield,
+            *player_entity,
+            &ecs.read_storage::<Equipped>(),
+            &ecs.read_storage::<ShieldBonus>(),
+        );
+        let shield_text = format!("Shield: {}/{}", shield.shield, capacity);
+        ctx.print_color(
+            38,
+            43,
+            RGB::named(rltk::DEEPSKYBLUE),
+            RGB::named(rltk::BLACK),
+            &shield_text,
+        );
+        ctx.draw_bar_horizontal(
+            52,
+            43,
+            6,
+            shield.shield,
+            capacity,
+            RGB::named(rltk::DEEPSKYBLUE),
+            RGB::named(rltk::BLACK),
+        );
+    }
+
+    let energy = ecs.read_storage::<Energy>();
+    if let Some(energy) = energy.get(*player_entity) {
+        let energy_text = format!("Energy: {}/{}", energy.energy, energy.max_energy);
+        ctx.print_color(
+            59,
+            43,
+            RGB::named(rltk::YELLOW),
+            RGB::named(rltk::BLACK),
+            &energy_text,
+        );
+        ctx.draw_bar_horizontal(
+            73,
+            43,
+            6,
+            energy.energy,
+            energy.max_energy,
+            RGB::named(rltk::YELLOW),
+            RGB::named(rltk::BLACK),
+        );
+    }
+
+    draw_weapon_status(ecs, ctx);
+    draw_abilities(ecs, ctx);
+
     let log = ecs.fetch::<GameLog>();
-    let mut y = 44;
-    for s in log.entries.iter().rev() {
+    for (y, s) in (44..).zip(log.entries.iter().rev()) {
         if y < 49 {
             ctx.print(2, y, s);
         }
-        y += 1;
     }
 
     let mouse_pos = ctx.mouse_pos();
@@ -56,10 +121,117 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     draw_tooltips(ecs, ctx);
 }
 
+// Equipped gun and spare rounds, along the bottom edge of the log box.
+fn draw_weapon_status(ecs: &World, ctx: &mut Rltk) {
+    let player_entity = ecs.fetch::<Entity>();
+    let entities = ecs.entities();
+    let equipped = ecs.read_storage::<Equipped>();
+    let guns = ecs.read_storage::<Gun>();
+    let names = ecs.read_storage::<Name>();
+    let ammo = ecs.read_storage::<Ammo>();
+    let backpack = ecs.read_storage::<InBackpack>();
+
+    let weapon = equipped_weapon(&entities, &equipped, *player_entity);
+    if let Some((weapon, gun)) = weapon.and_then(|weapon| guns.get(weapon).map(|g| (weapon, g))) {
+        let spare: i32 = (&ammo, &backpack)
+            .join()
+            .filter(|(_, pack)| pack.owner == *player_entity)
+            .map(|(rounds, _)| rounds.rounds)
+            .sum();
+        let status = format!(
+            " {}: {}/{} + {} ",
+            names.get(weapon).unwrap().name,
+            gun.ammo,
+            gun.max_ammo,
+            spare
+        );
+        ctx.print_color(
+            2,
+            49,
+            RGB::named(rltk::SILVER),
+            RGB::named(rltk::BLACK),
+            &status,
+        );
+    }
+}
+
+// Ability hotkeys along the bottom edge, greyed out while recharging or unaffordable.
+fn draw_abilities(ecs: &World, ctx: &mut Rltk) {
+    let player_entity = ecs.fetch::<Entity>();
+    let abilities = ecs.read_storage::<Ability>();
+    let names = ecs.read_storage::<Name>();
+    let available = ecs
+        .read_storage::<Energy>()
+        .get(*player_entity)
+        .map_or(0, |e| e.energy);
+
+    let mut owned: Vec<(&Ability, &Name)> = (&abilities, &names)
+        .join()
+        .filter(|(ability, _)| ability.owner == *player_entity)
+        .collect();
+    owned.sort_by_key(|(ability, _)| ability.hotkey);
+
+    let mut x = 27;
+    for (ability, name) in owned.iter() {
+        let label = if ability.cooldown_remaining > 0 {
+            format!(
+                " {}:{}({}) ",
+                ability.hotkey, name.name, ability.cooldown_remaining
+            )
+        } else {
+            format!(" {}:{} ", ability.hotkey, name.name)
+        };
+        let fg = if ability.cooldown_remaining > 0 || available < ability.energy_cost {
+            RGB::named(rltk::GREY)
+        } else {
+            RGB::named(rltk::YELLOW)
+        };
+        ctx.print_color(x, 49, fg, RGB::named(rltk::BLACK), &label);
+        x += label.len() as i32;
+    }
+}
+
+// A backpack in menu order, with items of the same name next to each other.
+fn backpack_contents(ecs: &World, owner: Entity) -> Vec<(Entity, String)> {
+    let names = ecs.read_storage::<Name>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let entities = ecs.entities();
+
+    let mut contents: Vec<(Entity, String)> = (&entities, &backpack, &names)
+        .join()
+        .filter(|item| item.1.owner == owner)
+        .map(|(entity, _, _)| (entity, identification_system::display_name(ecs, entity)))
+        .collect();
+    contents.sort_by(|a, b| a.1.cmp(&b.1));
+    contents
+}
+
+// Inventory line for an item, with whatever state is worth knowing at a glance.
+fn item_label(ecs: &World, item: Entity, name: &str) -> String {
+    let label = if ecs.read_storage::<Equipped>().get(item).is_some() {
+        format!("{} (equipped)", name)
+    } else if let Some(ammo) = ecs.read_storage::<Ammo>().get(item) {
+        format!("{} ({})", name, ammo.rounds)
+    } else if let Some(implant) = ecs.read_storage::<Implant>().get(item) {
+        format!("{} <{}>", name, implant.slot)
+    } else if let Some(charges) = ecs.read_storage::<Charges>().get(item) {
+        format!("{} [{}/{}]", name, charges.current, charges.max)
+    } else if let Some(stack) = ecs.read_storage::<Stackable>().get(item) {
+        format!("{} x{}", name, stack.quantity)
+    } else {
+        name.to_string()
+    };
+    match ecs.read_storage::<Malware>().get(item) {
+        Some(infection) if infection.revealed => format!("{} (infected)", label),
+        _ => label,
+    }
+}
+
 fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
     let map = ecs.fetch::<Map>();
     let names = ecs.read_storage::<Name>();
     let positions = ecs.read_storage::<Position>();
+    let items_storage = ecs.read_storage::<Item>();
 
     let mouse_pos = ctx.mouse_pos();
 
@@ -69,13 +241,27 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
 
     let mut tooltip: Vec<String> = Vec::new();
 
-    for (name, position) in (&names, &positions).join() {
+    let mut items: Vec<String> = Vec::new();
+    for (entity, _name, position) in (&ecs.entities(), &names, &positions).join() {
         if position.x == mouse_pos.0 && position.y == mouse_pos.1 {
-            tooltip.push(name.name.to_string());
+            let name = identification_system::display_name(ecs, entity);
             // if mouse is over enity with name and position. eg, enemies or player(s)
+            if items_storage.get(entity).is_some() {
+                items.push(item_label(ecs, entity, &name));
+            } else {
+                tooltip.push(name);
+            }
         }
     }
+    // every item on the tile, after whatever is standing on it
+    items.sort();
+    tooltip.extend(items);
 
+    draw_tooltip_box(ctx, mouse_pos, &tooltip);
+}
+
+// Draws lines of text next to a map position, on whichever side has more room.
+fn draw_tooltip_box(ctx: &mut Rltk, pos: (i32, i32), tooltip: &[String]) {
     if !tooltip.is_empty() {
         let mut width: i32 = 0;
         for s in tooltip.iter() {
@@ -85,11 +271,10 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
         }
         width += 3;
 
-        if mouse_pos.0 > 40 {
-            let arrow_pos = Point::new(mouse_pos.0 - 2, mouse_pos.1);
-            let left_x = mouse_pos.0 - width;
-            let mut y = mouse_pos.1;
-            for s in tooltip.iter() {
+        if pos.0 > 40 {
+            let arrow_pos = Point::new(pos.0 - 2, pos.1);
+            let left_x = pos.0 - width;
+            for (y, s) in (pos.1..).zip(tooltip.iter()) {
                 ctx.print_color(left_x, y, RGB::named(rltk::RED), RGB::named(rltk::BLACK), s);
                 let padding = (width - s.len() as i32) - 1;
                 for i in 0..padding {
@@ -98,23 +283,21 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
                         y,
                         RGB::named(rltk::WHITE),
                         RGB::named(rltk::BLACK),
-                        &" ".to_string(),
+                        " ",
                     );
                 }
-                y += 1;
             }
             ctx.print_color(
                 arrow_pos.x,
                 arrow_pos.y,
                 RGB::named(rltk::WHITE),
                 RGB::named(rltk::BLACK),
-                &"->".to_string(),
+                "->",
             );
         } else {
-            let arrow_pos = Point::new(mouse_pos.0 + 1, mouse_pos.1);
-            let left_x = mouse_pos.0 + 3;
-            let mut y = mouse_pos.1;
-            for s in tooltip.iter() {
+            let arrow_pos = Point::new(pos.0 + 1, pos.1);
+            let left_x = pos.0 + 3;
+            for (y, s) in (pos.1..).zip(tooltip.iter()) {
                 ctx.print_color(
                     left_x + 1,
                     y,
@@ -129,17 +312,16 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
                         y,
                         RGB::named(rltk::WHITE),
                         RGB::named(rltk::BLACK),
-                        &" ".to_string(),
+                        " ",
                     );
                 }
-                y += 1;
             }
             ctx.print_color(
                 arrow_pos.x,
                 arrow_pos.y,
                 RGB::named(rltk::WHITE),
                 RGB::named(rltk::BLACK),
-                &"<-".to_string(),
+                "<-",
             );
         }
     }
@@ -154,16 +336,10 @@ pub enum ItemMenuResult {
 
 pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
-    let names = gs.ecs.read_storage::<Name>();
-    let backpack = gs.ecs.read_storage::<InBackpack>();
-    let entities = gs.ecs.entities();
-
-    let inventory = (&backpack, &names)
-        .join()
-        .filter(|item| item.0.owner == *player_entity);
-    let count = inventory.count();
+    let inventory = backpack_contents(&gs.ecs, *player_entity);
+    let count = inventory.len();
 
-    let mut y = (25 - (count / 2)) as i32;
+    let y = (25 - (count / 2)) as i32;
     ctx.draw_box(
         15,
         y - 2,
@@ -179,6 +355,38 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
         RGB::named(rltk::BLACK),
         "Inventory",
     );
+    if let Some(humanity) = gs.ecs.read_storage::<Humanity>().get(*player_entity) {
+        let humanity_text = format!("Humanity: {}/{}", humanity.humanity, humanity.max_humanity);
+        ctx.print_color(
+            30,
+            y - 2,
+            RGB::named(rltk::HOTPINK),
+            RGB::named(rltk::BLACK),
+            &humanity_text,
+        );
+    }
+    if let Some(stats) = gs.ecs.read_storage::<CombatStats>().get(*player_entity) {
+        let load = encumbrance_system::carried_weight(
+            &gs.ecs.read_storage::<InBackpack>(),
+            &gs.ecs.read_storage::<Weight>(),
+            &gs.ecs.read_storage::<Stackable>(),
+            *player_entity,
+        );
+        let capacity = encumbrance_system::carry_capacity(stats);
+        let load_color = if load > capacity {
+            RGB::named(rltk::RED)
+        } else {
+            RGB::named(rltk::HOTPINK)
+        };
+        let load_text = format!("{:.1}/{:.0} kg", load, capacity);
+        ctx.print_color(
+            33,
+            y + count as i32 + 1,
+            load_color,
+            RGB::named(rltk::BLACK),
+            &load_text,
+        );
+    }
     ctx.print_color(
         18,
         y + count as i32 + 1,
@@ -188,12 +396,8 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
     );
 
     let mut equippable: Vec<Entity> = Vec::new();
-    let mut j = 0;
-
-    for (entity, _pack, name) in (&entities, &backpack, &names)
-        .join()
-        .filter(|item| item.1.owner == *player_entity)
-    {
+    for (j, (entity, name)) in inventory.iter().enumerate() {
+        let y = y + j as i32;
         ctx.set(
             17,
             y,
@@ -216,10 +420,8 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.name.to_string());
-        equippable.push(entity);
-        y += 1;
-        j += 1;
+        ctx.print(21, y, item_label(&gs.ecs, *entity, name));
+        equippable.push(*entity);
     }
 
     match ctx.key {
@@ -240,19 +442,424 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
     }
 }
 
+#[derive(PartialEq, Copy, Clone)]
+pub enum VendorMode {
+    Buy,
+    Sell,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum VendorResult {
+    NoResponse,
+    Cancel,
+    SwitchMode,
+    Buy,
+    Sell,
+}
+
+// Buy from the vendor's stock or sell from the backpack, Tab flips between the two.
+pub fn show_vendor(
+    gs: &mut State,
+    ctx: &mut Rltk,
+    vendor: Entity,
+    mode: VendorMode,
+) -> (VendorResult, Option<Entity>) {
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    let values = gs.ecs.read_storage::<Value>();
+    let wallet = gs
+        .ecs
+        .read_storage::<Credits>()
+        .get(player_entity)
+        .map_or(0, |c| c.amount);
+    let vendor_name = identification_system::display_name(&gs.ecs, vendor);
+
+    let (inventory, title) = match mode {
+        VendorMode::Buy => (
+            backpack_contents(&gs.ecs, vendor),
+            format!("{}: buying", vendor_name),
+        ),
+        VendorMode::Sell => (
+            backpack_contents(&gs.ecs, player_entity),
+            format!("{}: selling", vendor_name),
+        ),
+    };
+    let count = inventory.len();
+
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(
+        15,
+        y - 2,
+        51,
+        (count + 3) as i32,
+        RGB::named(rltk::GOLD),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        18,
+        y - 2,
+        RGB::named(rltk::HOTPINK),
+        RGB::named(rltk::BLACK),
+        &title,
+    );
+    let wallet_text = format!("${}", wallet);
+    ctx.print_color(
+        64 - wallet_text.len() as i32,
+        y - 2,
+        RGB::named(rltk::GOLD),
+        RGB::named(rltk::BLACK),
+        &wallet_text,
+    );
+    ctx.print_color(
+        18,
+        y + count as i32 + 1,
+        RGB::named(rltk::HOTPINK),
+        RGB::named(rltk::BLACK),
+        "tab to switch, esc to leave.",
+    );
+
+    let mut tradeable: Vec<Entity> = Vec::new();
+    for (j, (entity, name)) in inventory.iter().enumerate() {
+        let y = y + j as i32;
+        ctx.set(
+            17,
+            y,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437('('),
+        );
+        ctx.set(
+            18,
+            y,
+            RGB::named(rltk::GOLD),
+            RGB::named(rltk::BLACK),
+            97 + j as rltk::FontCharType,
+        );
+        ctx.set(
+            19,
+            y,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437(')'),
+        );
+        ctx.print(21, y, item_label(&gs.ecs, *entity, name));
+
+        let price = match (mode, values.get(*entity)) {
+            (_, None) => "-".to_string(),
+            (VendorMode::Buy, Some(value)) => format!("${}", value.credits),
+            (VendorMode::Sell, Some(value)) => format!("${}", value.credits / 2),
+        };
+        ctx.print(64 - price.len() as i32, y, &price);
+        tradeable.push(*entity);
+    }
+
+    match ctx.key {
+        None => (VendorResult::NoResponse, None),
+        Some(key) => match key {
+            VirtualKeyCode::Escape => (VendorResult::Cancel, None),
+            VirtualKeyCode::Tab => (VendorResult::SwitchMode, None),
+            _ => {
+                let selection = rltk::letter_to_option(key);
+                if selection > -1 && selection < count as i32 {
+                    let result = match mode {
+                        VendorMode::Buy => VendorResult::Buy,
+                        VendorMode::Sell => VendorResult::Sell,
+                    };
+                    return (result, Some(tradeable[selection as usize]));
+                }
+                (VendorResult::NoResponse, None)
+            }
+        },
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum ContainerMode {
+    Take,
+    Put,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum ContainerResult {
+    NoResponse,
+    Cancel,
+    SwitchMode,
+    Take,
+    Put,
+}
+
+// Take from the container or put from the backpack, Tab flips between the two.
+pub fn show_container(
+    gs: &mut State,
+    ctx: &mut Rltk,
+    container: Entity,
+    mode: ContainerMode,
+) -> (ContainerResult, Option<Entity>) {
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    let container_name = identification_system::display_name(&gs.ecs, container);
+
+    let (inventory, title) = match mode {
+        ContainerMode::Take => (
+            backpack_contents(&gs.ecs, container),
+            format!("{}: taking", container_name),
+        ),
+        ContainerMode::Put => (
+            backpack_contents(&gs.ecs, player_entity),
+            format!("{}: putting away", container_name),
+        ),
+    };
+    let count = inventory.len();
+
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(
+        15,
+        y - 2,
+        51,
+        (count + 3) as i32,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        18,
+        y - 2,
+        RGB::named(rltk::HOTPINK),
+        RGB::named(rltk::BLACK),
+        &title,
+    );
+    ctx.print_color(
+        18,
+        y + count as i32 + 1,
+        RGB::named(rltk::HOTPINK),
+        RGB::named(rltk::BLACK),
+        "tab to switch, esc to close.",
+    );
+
+    let mut contents: Vec<Entity> = Vec::new();
+    for (j, (entity, name)) in inventory.iter().enumerate() {
+        let y = y + j as i32;
+        ctx.set(
+            17,
+            y,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437('('),
+        );
+        ctx.set(
+            18,
+            y,
+            RGB::named(rltk::YELLOW),
+            RGB::named(rltk::BLACK),
+            97 + j as rltk::FontCharType,
+        );
+        ctx.set(
+            19,
+            y,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437(')'),
+        );
+        ctx.print(21, y, item_label(&gs.ecs, *entity, name));
+        contents.push(*entity);
+    }
+
+    match ctx.key {
+        None => (ContainerResult::NoResponse, None),
+        Some(key) => match key {
+            VirtualKeyCode::Escape => (ContainerResult::Cancel, None),
+            VirtualKeyCode::Tab => (ContainerResult::SwitchMode, None),
+            _ => {
+                let selection = rltk::letter_to_option(key);
+                if selection > -1 && selection < count as i32 {
+                    let result = match mode {
+                        ContainerMode::Take => ContainerResult::Take,
+                        ContainerMode::Put => ContainerResult::Put,
+                    };
+                    return (result, Some(contents[selection as usize]));
+                }
+                (ContainerResult::NoResponse, None)
+            }
+        },
+    }
+}
+
+// Recipes the player has the parts for are lit up, the rest are greyed out.
+pub fn show_crafting(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<usize>) {
+    let recipes = gs.ecs.fetch::<crafting::Recipes>();
+    let count = recipes.recipes.len();
+
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(
+        15,
+        y - 2,
+        55,
+        (count + 3) as i32,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        18,
+        y - 2,
+        RGB::named(rltk::HOTPINK),
+        RGB::named(rltk::BLACK),
+        "Craft what?",
+    );
+    ctx.print_color(
+        18,
+        y + count as i32 + 1,
+        RGB::named(rltk::HOTPINK),
+        RGB::named(rltk::BLACK),
+        "ESCAPE to cancel",
+    );
+
+    for (j, recipe) in recipes.recipes.iter().enumerate() {
+        let y = y + j as i32;
+        let fg = if crafting::can_craft(&gs.ecs, recipe) {
+            RGB::named(rltk::WHITE)
+        } else {
+            RGB::named(rltk::DIM_GREY)
+        };
+        ctx.set(
+            17,
+            y,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437('('),
+        );
+        ctx.set(
+            18,
+            y,
+            RGB::named(rltk::YELLOW),
+            RGB::named(rltk::BLACK),
+            97 + j as rltk::FontCharType,
+        );
+        ctx.set(
+            19,
+            y,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437(')'),
+        );
+        let inputs: Vec<String> = recipe
+            .inputs
+            .iter()
+            .map(|input| format!("{}x {}", input.quantity, input.name))
+            .collect();
+        ctx.print_color(
+            21,
+            y,
+            fg,
+            RGB::named(rltk::BLACK),
+            format!("{}: {}", recipe.output, inputs.join(", ")),
+        );
+    }
+
+    match ctx.key {
+        None => (ItemMenuResult::NoResponse, None),
+        Some(key) => match key {
+            VirtualKeyCode::Escape => (ItemMenuResult::Cancel, None),
+            _ => {
+                let selection = rltk::letter_to_option(key);
+                if selection > -1 && selection < count as i32 {
+                    return (ItemMenuResult::Selected, Some(selection as usize));
+                }
+                (ItemMenuResult::NoResponse, None)
+            }
+        },
+    }
+}
+
+// Everything on the player's tile, one letter each, or Enter for the lot.
+pub fn show_pickup(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Vec<Entity>) {
+    let mut items: Vec<(Entity, String)> = player::items_underfoot(&gs.ecs)
+        .into_iter()
+        .map(|item| (item, identification_system::display_name(&gs.ecs, item)))
+        .collect();
+    items.sort_by(|a, b| a.1.cmp(&b.1));
+    let count = items.len();
+
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(
+        15,
+        y - 2,
+        31,
+        (count + 3) as i32,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        18,
+        y - 2,
+        RGB::named(rltk::HOTPINK),
+        RGB::named(rltk::BLACK),
+        "Pick up what?",
+    );
+    ctx.print_color(
+        18,
+        y + count as i32 + 1,
+        RGB::named(rltk::HOTPINK),
+        RGB::named(rltk::BLACK),
+        "enter for all, esc to cancel.",
+    );
+
+    for (j, (entity, name)) in items.iter().enumerate() {
+        let y = y + j as i32;
+        ctx.set(
+            17,
+            y,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437('('),
+        );
+        ctx.set(
+            18,
+            y,
+            RGB::named(rltk::YELLOW),
+            RGB::named(rltk::BLACK),
+            97 + j as rltk::FontCharType,
+        );
+        ctx.set(
+            19,
+            y,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437(')'),
+        );
+        ctx.print(21, y, item_label(&gs.ecs, *entity, name));
+    }
+
+    match ctx.key {
+        None => (ItemMenuResult::NoResponse, Vec::new()),
+        Some(key) => match key {
+            VirtualKeyCode::Escape => (ItemMenuResult::Cancel, Vec::new()),
+            VirtualKeyCode::Return => (
+                ItemMenuResult::Selected,
+                items.iter().map(|(entity, _)| *entity).collect(),
+            ),
+            _ => {
+                let selection = rltk::letter_to_option(key);
+                if selection > -1 && selection < count as i32 {
+                    return (ItemMenuResult::Selected, vec![items[selection as usize].0]);
+                }
+                (ItemMenuResult::NoResponse, Vec::new())
+            }
+        },
+    }
+}
+
 pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
-    let player_entity = gs.ecs.fetch::<Entity>();
-    let names = gs.ecs.read_storage::<Name>();
-    let backpack = gs.ecs.read_storage::<InBackpack>();
-    let entities = gs.ecs.entities();
+    backpack_menu(gs, ctx, "Drop what?")
+}
 
-    let inventory = (&backpack, &names)
-        .join()
-        .filter(|item| item.0.owner == *player_entity);
-    let count = inventory.count();
+pub fn throw_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    backpack_menu(gs, ctx, "Throw what?")
+}
+
+// Picks one item from the player's backpack.
+fn backpack_menu(gs: &mut State, ctx: &mut Rltk, title: &str) -> (ItemMenuResult, Option<Entity>) {
+    let inventory = backpack_contents(&gs.ecs, *gs.ecs.fetch::<Entity>());
+    let count = inventory.len();
 
     // Draw Gui
-    let mut y = (25 - (count / 2)) as i32;
+    let y = (25 - (count / 2)) as i32;
     ctx.draw_box(
         15,
         y - 2,
@@ -266,7 +873,7 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
         y - 2,
         RGB::named(rltk::HOTPINK),
         RGB::named(rltk::BLACK),
-        "Drop what?",
+        title,
     );
     ctx.print_color(
         18,
@@ -277,12 +884,8 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
     );
 
     let mut equippable: Vec<Entity> = Vec::new();
-    let mut z = 0;
-
-    for (entity, _pack, name) in (&entities, &backpack, &names)
-        .join()
-        .filter(|item| item.1.owner == *player_entity)
-    {
+    for (z, (entity, name)) in inventory.iter().enumerate() {
+        let y = y + z as i32;
         ctx.set(
             17,
             y,
@@ -304,10 +907,8 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
             RGB::named(rltk::BLACK),
             rltk::to_cp437(')'),
         );
-        ctx.print(21, y, &name.name.to_string());
-        equippable.push(entity);
-        y += 1;
-        z += 1;
+        ctx.print(21, y, item_label(&gs.ecs, *entity, name));
+        equippable.push(*entity);
     }
 
     match ctx.key {
@@ -328,59 +929,155 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
     }
 }
 
+// Movement keys from player_input, reused to steer the targeting and look cursor.
+fn cursor_delta(key: VirtualKeyCode) -> Option<(i32, i32)> {
+    match key {
+        VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => Some((-1, 0)),
+        VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => Some((1, 0)),
+        VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => Some((0, -1)),
+        VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => Some((0, 1)),
+        VirtualKeyCode::Numpad9 | VirtualKeyCode::Y => Some((1, -1)),
+        VirtualKeyCode::Numpad7 | VirtualKeyCode::U => Some((-1, -1)),
+        VirtualKeyCode::Numpad3 | VirtualKeyCode::N => Some((1, 1)),
+        VirtualKeyCode::Numpad1 | VirtualKeyCode::B => Some((-1, 1)),
+        _ => None,
+    }
+}
+
+// Monsters the player can currently see, nearest first.
+fn visible_hostiles(ecs: &World) -> Vec<Point> {
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let monsters = ecs.read_storage::<Monster>();
+    let positions = ecs.read_storage::<Position>();
+
+    let mut hostiles: Vec<Point> = (&monsters, &positions)
+        .join()
+        .filter(|(_, pos)| map.visible_tiles[map.xy_idx(pos.x, pos.y)])
+        .map(|(_, pos)| Point::new(pos.x, pos.y))
+        .collect();
+    hostiles.sort_by(|a, b| {
+        let da = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *a);
+        let db = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *b);
+        da.partial_cmp(&db).unwrap()
+    });
+    hostiles
+}
+
+// Moves the cursor for a key press: Tab jumps to the next hostile, movement keys step it.
+fn steer_cursor(cursor: Point, key: VirtualKeyCode, hostiles: &[Point], map: &Map) -> Point {
+    if key == VirtualKeyCode::Tab {
+        if hostiles.is_empty() {
+            return cursor;
+        }
+        let next = match hostiles.iter().position(|h| *h == cursor) {
+            Some(current) => (current + 1) % hostiles.len(),
+            None => 0,
+        };
+        return hostiles[next];
+    }
+    match cursor_delta(key) {
+        None => cursor,
+        Some((dx, dy)) => Point::new(
+            (cursor.x + dx).clamp(0, map.width - 1),
+            (cursor.y + dy).clamp(0, map.height - 1),
+        ),
+    }
+}
+
 pub fn ranged_target(
     gs: &mut State,
     ctx: &mut Rltk,
     range: i32,
 ) -> (ItemMenuResult, Option<Point>) {
-    let player_entity = gs.ecs.fetch::<Entity>();
-    let player_pos = gs.ecs.fetch::<Point>();
-    let viewsheds = gs.ecs.read_storage::<Viewshed>();
+    let player_pos = *gs.ecs.fetch::<Point>();
+    let mut available_cells = Vec::new(); // possible cells to target
+    {
+        let player_entity = gs.ecs.fetch::<Entity>();
+        let viewsheds = gs.ecs.read_storage::<Viewshed>();
+        let map = gs.ecs.fetch::<Map>();
+        let visible = viewsheds.get(*player_entity);
+
+        if let Some(visible) = visible {
+            // We have a viewshed
+            for idx in visible.visible_tiles.iter() {
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(player_pos, *idx);
+                // can't aim at what's hidden in the dark
+                if distance <= range as f32 && map.visible_tiles[map.xy_idx(idx.x, idx.y)] {
+                    available_cells.push(*idx);
+                }
+            }
+        } else {
+            gs.cursor = None;
+            return (ItemMenuResult::Cancel, None);
+        }
+    }
+    let hostiles: Vec<Point> = visible_hostiles(&gs.ecs)
+        .into_iter()
+        .filter(|h| available_cells.contains(h))
+        .collect();
+
+    // the cursor starts on the nearest hostile in range, or on the player
+    let mut cursor = gs
+        .cursor
+        .unwrap_or_else(|| hostiles.first().copied().unwrap_or(player_pos));
+    if let Some(key) = ctx.key {
+        match key {
+            VirtualKeyCode::Escape => {
+                gs.cursor = None;
+                return (ItemMenuResult::Cancel, None);
+            }
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                if available_cells.contains(&cursor) {
+                    gs.cursor = None;
+                    return (ItemMenuResult::Selected, Some(cursor));
+                }
+            }
+            _ => cursor = steer_cursor(cursor, key, &hostiles, &gs.ecs.fetch::<Map>()),
+        }
+    }
+    gs.cursor = Some(cursor);
 
     ctx.print_color(
         5,
         0,
         RGB::named(rltk::YELLOW),
         RGB::named(rltk::BLACK),
-        "Select Target:",
+        "Select Target: tab cycles, enter confirms, esc cancels",
     );
-
-    let mut available_cells = Vec::new(); // possible cells to raget
-    let visible = viewsheds.get(*player_entity);
-
-    if let Some(visible) = visible {
-        // We have a viewshed
-        for idx in visible.visible_tiles.iter() {
-            let distance = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *idx);
-            if distance <= range as f32 {
-                ctx.set_bg(idx.x, idx.y, RGB::named(rltk::BLUE)); // if postion(mouse) is within range, highlight
-                available_cells.push(idx);
-            }
+    for idx in available_cells.iter() {
+        ctx.set_bg(idx.x, idx.y, RGB::named(rltk::BLUE));
+    }
+    // show where a shot at the cursor would actually stop
+    {
+        let map = gs.ecs.fetch::<Map>();
+        let impact = map.projectile_impact(player_pos, cursor);
+        for point in rltk::line2d(rltk::LineAlg::Bresenham, player_pos, impact)
+            .iter()
+            .skip(1)
+        {
+            ctx.set_bg(point.x, point.y, RGB::named(rltk::DARK_CYAN));
         }
+    }
+    if available_cells.contains(&cursor) {
+        ctx.set_bg(cursor.x, cursor.y, RGB::named(rltk::CYAN));
     } else {
-        return (ItemMenuResult::Cancel, None);
+        ctx.set_bg(cursor.x, cursor.y, RGB::named(rltk::RED));
     }
 
-    // Draw mouse cursor
+    // the mouse still works as before
     let mouse_pos = ctx.mouse_pos();
-    let mut valid_target = false;
-    for idx in available_cells.iter() {
-        if idx.x == mouse_pos.0 && idx.y == mouse_pos.1 {
-            valid_target = true;
-        }
-    }
-    // check cell of mouse position
-    if valid_target {
+    let mouse_point = Point::new(mouse_pos.0, mouse_pos.1);
+    if available_cells.contains(&mouse_point) {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::CYAN));
         if ctx.left_click {
-            return (
-                ItemMenuResult::Selected,
-                Some(Point::new(mouse_pos.0, mouse_pos.1)),
-            );
+            gs.cursor = None;
+            return (ItemMenuResult::Selected, Some(mouse_point));
         }
     } else {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::RED));
         if ctx.left_click {
+            gs.cursor = None;
             return (ItemMenuResult::Cancel, None);
         }
     }
@@ -388,6 +1085,78 @@ pub fn ranged_target(
     (ItemMenuResult::NoResponse, None)
 }
 
+// Look mode: a free cursor that describes whatever is under it. Cancel means the player is done.
+pub fn look(gs: &mut State, ctx: &mut Rltk) -> ItemMenuResult {
+    let player_pos = *gs.ecs.fetch::<Point>();
+    let hostiles = visible_hostiles(&gs.ecs);
+
+    let mut cursor = gs.cursor.unwrap_or(player_pos);
+    if let Some(key) = ctx.key {
+        match key {
+            VirtualKeyCode::Escape | VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                gs.cursor = None;
+                return ItemMenuResult::Cancel;
+            }
+            _ => cursor = steer_cursor(cursor, key, &hostiles, &gs.ecs.fetch::<Map>()),
+        }
+    }
+    gs.cursor = Some(cursor);
+
+    ctx.print_color(
+        5,
+        0,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Look: tab cycles hostiles, esc exits",
+    );
+    ctx.set_bg(cursor.x, cursor.y, RGB::named(rltk::MAGENTA));
+    draw_tooltip_box(ctx, (cursor.x, cursor.y), &describe_tile(&gs.ecs, cursor));
+
+    ItemMenuResult::NoResponse
+}
+
+// What the player knows about a tile: what's standing on it if they can see it, and the tile
+// itself if they've been there.
+fn describe_tile(ecs: &World, point: Point) -> Vec<String> {
+    let map = ecs.fetch::<Map>();
+    let names = ecs.read_storage::<Name>();
+    let positions = ecs.read_storage::<Position>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let entities = ecs.entities();
+
+    let idx = map.xy_idx(point.x, point.y);
+    if !map.revealed_tiles[idx] {
+        return vec!["Unexplored".to_string()];
+    }
+
+    let mut description: Vec<String> = Vec::new();
+    if map.visible_tiles[idx] {
+        for (entity, _name, pos) in (&entities, &names, &positions).join() {
+            if pos.x != point.x || pos.y != point.y {
+                continue;
+            }
+            let name = identification_system::display_name(ecs, entity);
+            match combat_stats.get(entity) {
+                Some(stats) => {
+                    description.push(format!("{} ({}/{})", name, stats.hp, stats.max_hp))
+                }
+                None => description.push(name),
+            }
+        }
+    }
+    let tile = match map.tiles[idx] {
+        TileType::Wall => "Wall",
+        TileType::Floor => "Floor",
+        TileType::DownStairs => "Service lift down",
+    };
+    if map.visible_tiles[idx] {
+        description.push(tile.to_string());
+    } else {
+        description.push(format!("{} (remembered)", tile));
+    }
+    description
+}
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum MainMenuSelection {
     NewGame,
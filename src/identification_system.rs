@@ -0,0 +1,129 @@
+use super::{
+    gamelog::GameLog, InBackpack, Malware, Name, ObfuscatedName, ProvidesIdentification,
+    WantsToUseItem,
+};
+use rltk::RandomNumberGenerator;
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+// What the player has worked out about items this run. Every kind of chip gets a random code the
+// first time one is spawned, and keeps it until the game ends.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct ItemKnowledge {
+    pub codes: HashMap<String, String>,
+    pub known: HashSet<String>,
+}
+
+impl ItemKnowledge {
+    pub fn code_for(&mut self, real_name: &str, rng: &mut RandomNumberGenerator) -> String {
+        if let Some(code) = self.codes.get(real_name) {
+            return code.clone();
+        }
+        let mut code = String::new();
+        while code.is_empty() || self.codes.values().any(|c| *c == code) {
+            code = format!("Encrypted Chip #{:03X}", rng.range(0, 4096));
+        }
+        self.codes.insert(real_name.to_string(), code.clone());
+        code
+    }
+
+    // The name to show for something, whether or not it's an item.
+    pub fn display_name(&self, name: &Name, obfuscated: Option<&ObfuscatedName>) -> String {
+        match obfuscated {
+            Some(obfuscated) if !self.known.contains(&name.name) => obfuscated.name.clone(),
+            _ => name.name.clone(),
+        }
+    }
+}
+
+pub fn display_name(ecs: &World, entity: Entity) -> String {
+    let names = ecs.read_storage::<Name>();
+    match names.get(entity) {
+        None => String::new(),
+        Some(name) => ecs
+            .fetch::<ItemKnowledge>()
+            .display_name(name, ecs.read_storage::<ObfuscatedName>().get(entity)),
+    }
+}
+
+// Using an item shows what it really is. Decrypters crack everything in the user's backpack and
+// find any malware hiding in it.
+// Runs before the item is used up, so the rest of the turn already talks about it by name.
+pub struct IdentificationSystem {}
+
+impl<'a> System<'a> for IdentificationSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, ItemKnowledge>,
+        Entities<'a>,
+        ReadStorage<'a, WantsToUseItem>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, ObfuscatedName>,
+        ReadStorage<'a, ProvidesIdentification>,
+        ReadStorage<'a, InBackpack>,
+        WriteStorage<'a, Malware>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            mut knowledge,
+            entities,
+            wants_to_use,
+            names,
+            obfuscated,
+            provides_identification,
+            backpack,
+            mut malware,
+        ) = data;
+
+        // only the player learns anything
+        let useitem = match wants_to_use.get(*player_entity) {
+            None => return,
+            Some(useitem) => useitem,
+        };
+
+        let mut to_identify: Vec<Entity> = vec![useitem.item];
+        if provides_identification.get(useitem.item).is_some() {
+            for (item, pack) in (&entities, &backpack).join() {
+                if pack.owner == *player_entity {
+                    to_identify.push(item);
+                }
+            }
+        }
+
+        let mut cracked = 0;
+        let decrypting = provides_identification.get(useitem.item).is_some();
+        for item in to_identify.iter() {
+            if let (Some(name), Some(code)) = (names.get(*item), obfuscated.get(*item)) {
+                if knowledge.known.insert(name.name.clone()) {
+                    gamelog
+                        .entries
+                        .push(format!("The {} is a {}.", code.name, name.name));
+                    cracked += 1;
+                }
+            }
+
+            // the decrypter's scan turns up malware too
+            if let Some(infection) = malware.get_mut(*item) {
+                if decrypting && !infection.revealed {
+                    infection.revealed = true;
+                    gamelog.entries.push(format!(
+                        "The {} is infected!",
+                        names.get(*item).unwrap().name
+                    ));
+                    cracked += 1;
+                }
+            }
+        }
+        if cracked == 0 && decrypting {
+            gamelog
+                .entries
+                .push("The decrypter finds nothing new to crack.".to_string());
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use super::{
+    gamelog::GameLog, Ability, CombatStats, Energy, GrantsAbility, Humanity, Implant, ImplantBonus,
+    InBackpack, Installed, Name, Speed, Viewshed, WantsToUseItem,
+};
+use specs::prelude::*;
+
+// Abilities are triggered with 1-9.
+const MAX_HOTKEY: i32 = 9;
+// How much banked speed buys an extra turn.
+pub const TURN_COST: i32 = 4;
+
+// Using an implant installs it. Like equipping, this takes the intent away so the other item
+// systems never see it, unless the implant is already in and is being used as an ability.
+pub struct ImplantSystem {}
+
+impl<'a> System<'a> for ImplantSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToUseItem>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Implant>,
+        WriteStorage<'a, Installed>,
+        WriteStorage<'a, Humanity>,
+        WriteStorage<'a, InBackpack>,
+        ReadStorage<'a, ImplantBonus>,
+        WriteStorage<'a, Viewshed>,
+        WriteStorage<'a, CombatStats>,
+        WriteStorage<'a, Energy>,
+        WriteStorage<'a, Speed>,
+        ReadStorage<'a, GrantsAbility>,
+        WriteStorage<'a, Ability>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            entities,
+            mut wants_to_use,
+            names,
+            implants,
+            mut installed,
+            mut humanity,
+            mut backpack,
+            bonuses,
+            mut viewsheds,
+            mut combat_stats,
+            mut energy,
+            mut speeds,
+            grants_ability,
+            mut abilities,
+        ) = data;
+
+        let mut to_remove: Vec<Entity> = Vec::new();
+        for (entity, useitem) in (&entities, &wants_to_use).join() {
+            let implant = match implants.get(useitem.item) {
+                Some(implant) if installed.get(useitem.item).is_none() => implant,
+                _ => continue,
+            };
+            to_remove.push(entity);
+            let is_player = entity == *player_entity;
+            let item_name = &names.get(useitem.item).unwrap().name;
+
+            let socket_taken = (&installed)
+                .join()
+                .any(|i| i.owner == entity && i.slot == implant.slot);
+            if socket_taken {
+                if is_player {
+                    gamelog
+                        .entries
+                        .push(format!("Your {} socket is already taken.", implant.slot));
+                }
+                continue;
+            }
+            let remaining = humanity.get(entity).map_or(0, |h| h.humanity);
+            if remaining < implant.humanity_cost {
+                if is_player {
+                    gamelog.entries.push(format!(
+                        "You can't take the {}, there's not enough of you left ({}/{}).",
+                        item_name, remaining, implant.humanity_cost
+                    ));
+                }
+                continue;
+            }
+
+            // abilities live on the number keys, so there has to be one free
+            let hotkey = if grants_ability.get(useitem.item).is_some() {
+                let taken: Vec<i32> = (&abilities)
+                    .join()
+                    .filter(|a| a.owner == entity)
+                    .map(|a| a.hotkey)
+                    .collect();
+                match (1..=MAX_HOTKEY).find(|key| !taken.contains(key)) {
+                    Some(hotkey) => Some(hotkey),
+                    None => {
+                        if is_player {
+                            gamelog.entries.push(format!(
+                                "You can't take the {}, every ability key is in use.",
+                                item_name
+                            ));
+                        }
+                        continue;
+                    }
+                }
+            } else {
+                None
+            };
+
+            if let Some(humanity) = humanity.get_mut(entity) {
+                humanity.humanity -= implant.humanity_cost;
+            }
+            backpack.remove(useitem.item);
+            installed
+                .insert(
+                    useitem.item,
+                    Installed {
+                        owner: entity,
+                        slot: implant.slot,
+                    },
+                )
+                .expect("Unable to install");
+            if is_player {
+                gamelog
+                    .entries
+                    .push(format!("You install the {}.", item_name));
+            }
+
+            if let Some(bonus) = bonuses.get(useitem.item) {
+                if let Some(viewshed) = viewsheds.get_mut(entity) {
+                    viewshed.range += bonus.sight;
+                    viewshed.dirty = true;
+                }
+                if let Some(stats) = combat_stats.get_mut(entity) {
+                    stats.power += bonus.power;
+                    stats.evasion += bonus.evasion;
+                }
+                if let Some(energy) = energy.get_mut(entity) {
+                    energy.max_energy += bonus.max_energy;
+                }
+                if bonus.speed > 0 {
+                    match speeds.get_mut(entity) {
+                        Some(speed) => speed.speed += bonus.speed,
+                        None => {
+                            speeds
+                                .insert(
+                                    entity,
+                                    Speed {
+                                        speed: bonus.speed,
+                                        banked: 0,
+                                    },
+                                )
+                                .expect("Unable to speed up");
+                        }
+                    }
+                }
+            }
+
+            if let (Some(grant), Some(hotkey)) = (grants_ability.get(useitem.item), hotkey) {
+                abilities
+                    .insert(
+                        useitem.item,
+                        Ability {
+                            owner: entity,
+                            hotkey,
+                            energy_cost: grant.energy_cost,
+                            cooldown: grant.cooldown,
+                            cooldown_remaining: 0,
+                        },
+                    )
+                    .expect("Unable to grant ability");
+                if is_player {
+                    gamelog
+                        .entries
+                        .push(format!("New ability on key {}.", hotkey));
+                }
+            }
+        }
+
+        for entity in to_remove.iter() {
+            wants_to_use.remove(*entity);
+        }
+    }
+}
@@ -1,9 +1,309 @@
 use super::{
-    gamelog::GameLog, AreaOfEffect, CombatStats, Consumeable, Disable, InBackpack, InflictsDamage,
-    Map, Name, Position, ProvidesHealing, SufferDamage, WantsToDropItem, WantsToPickupItem,
-    WantsToUseItem,
+    encumbrance_system::{carried_weight, carry_capacity, HARD_CAP},
+    gamelog::GameLog,
+    identification_system::{self, ItemKnowledge},
+    particle_system::{damage_color, ParticleBuilder, BLAST_MS},
+    spawner, Ability, Ammo, AreaOfEffect, Charges, Cloaked, CombatStats, Consumeable, Credits,
+    DamageType, Disable, Equippable, Equipped, InBackpack, InflictsDamage, Malware, Map, Name,
+    ObfuscatedName, Overclocked, Position, ProvidesCloak, ProvidesHealing, ProvidesOverclock,
+    ProvidesRecharge, Stackable, SufferDamage, Value, WantsToDropItem, WantsToPickupItem,
+    WantsToThrowItem, WantsToUseItem, Weight,
 };
+use rltk::{DiceType, Point, RandomNumberGenerator, RGB};
 use specs::prelude::*;
+use specs::storage::MaskedStorage;
+use std::ops::Deref;
+
+// One stack per menu letter.
+pub const MAX_STACKS: usize = 26;
+
+// Takes one item off a stack as an entity of its own, still in the same backpack, so it can be
+// dropped or thrown without the rest of the stack. The single starts as a clean template of the
+// item's kind, so it gets every component a new item of its kind would; only state the stack
+// picked up after spawning is copied across. Anything the spawner can't make comes back unsplit.
+pub fn split_stack(ecs: &mut World, stack: Entity) -> Entity {
+    let quantity = match ecs.read_storage::<Stackable>().get(stack) {
+        Some(stackable) if stackable.quantity > 1 => stackable.quantity,
+        _ => return stack,
+    };
+    let real_name = ecs.read_storage::<Name>().get(stack).unwrap().name.clone();
+    let single = match spawner::item_template(ecs, &real_name) {
+        Some(single) => single,
+        None => return stack,
+    };
+    copy_component::<Malware>(ecs, stack, single);
+
+    let owner = ecs
+        .read_storage::<InBackpack>()
+        .get(stack)
+        .map(|pack| pack.owner);
+    if let Some(owner) = owner {
+        ecs.write_storage::<InBackpack>()
+            .insert(single, InBackpack { owner })
+            .expect("Unable to split stack");
+    }
+
+    let mut stackable = ecs.write_storage::<Stackable>();
+    stackable.get_mut(stack).unwrap().quantity = quantity - 1;
+    stackable
+        .insert(single, Stackable { quantity: 1 })
+        .expect("Unable to split stack");
+    single
+}
+
+fn copy_component<T: Component + Clone>(ecs: &World, from: Entity, to: Entity) {
+    let mut storage = ecs.write_storage::<T>();
+    if let Some(component) = storage.get(from).cloned() {
+        storage
+            .insert(to, component)
+            .expect("Unable to copy component");
+    }
+}
+
+// The stack in an owner's backpack that an item would fold into, if it's stackable and they
+// already carry one of its kind. Malware nobody has found yet doesn't keep stacks apart, since a
+// separate line in the inventory would give it away; the merged stack carries it instead.
+pub fn matching_stack<B, N, S, M>(
+    entities: &Entities,
+    backpack: &Storage<InBackpack, B>,
+    names: &Storage<Name, N>,
+    stackable: &Storage<Stackable, S>,
+    malware: &Storage<Malware, M>,
+    item: Entity,
+    owner: Entity,
+) -> Option<Entity>
+where
+    B: Deref<Target = MaskedStorage<InBackpack>>,
+    N: Deref<Target = MaskedStorage<Name>>,
+    S: Deref<Target = MaskedStorage<Stackable>>,
+    M: Deref<Target = MaskedStorage<Malware>>,
+{
+    stackable.get(item)?;
+    let item_name = &names.get(item).unwrap().name;
+    (entities, backpack, names, stackable)
+        .join()
+        .find(|(stack, pack, name, _)| {
+            *stack != item
+                && pack.owner == owner
+                && name.name == *item_name
+                && shows_infection(malware, *stack) == shows_infection(malware, item)
+        })
+        .map(|(stack, _, _, _)| stack)
+}
+
+fn shows_infection<M>(malware: &Storage<Malware, M>, item: Entity) -> bool
+where
+    M: Deref<Target = MaskedStorage<Malware>>,
+{
+    malware
+        .get(item)
+        .is_some_and(|infection| infection.revealed)
+}
+
+// Why an item won't go into a backpack.
+pub enum NoRoom {
+    Full,
+    TooHeavy,
+}
+
+impl NoRoom {
+    pub fn reason(&self, shown_name: &str) -> String {
+        match self {
+            NoRoom::Full => "Your backpack is full.".to_string(),
+            NoRoom::TooHeavy => format!("You can't carry the {} as well.", shown_name),
+        }
+    }
+}
+
+// Checks an owner can take an item: a free slot for it, unless it joins a stack they already
+// have, and the strength to carry the extra weight.
+pub fn check_room<B, S, W>(
+    backpack: &Storage<InBackpack, B>,
+    stackable: &Storage<Stackable, S>,
+    weights: &Storage<Weight, W>,
+    stats: Option<&CombatStats>,
+    item: Entity,
+    owner: Entity,
+    joins_stack: bool,
+) -> Result<(), NoRoom>
+where
+    B: Deref<Target = MaskedStorage<InBackpack>>,
+    S: Deref<Target = MaskedStorage<Stackable>>,
+    W: Deref<Target = MaskedStorage<Weight>>,
+{
+    if !joins_stack && backpack.join().filter(|pack| pack.owner == owner).count() >= MAX_STACKS {
+        return Err(NoRoom::Full);
+    }
+    if let Some(stats) = stats {
+        let load = carried_weight(backpack, weights, stackable, owner);
+        let item_weight = weights.get(item).map_or(0.0, |w| w.weight)
+            * stackable.get(item).map_or(1, |s| s.quantity) as f32;
+        if load + item_weight > carry_capacity(stats) * HARD_CAP {
+            return Err(NoRoom::TooHeavy);
+        }
+    }
+    Ok(())
+}
+
+// Puts an item into a backpack, folding it into a matching stack if there is one. Fails, with the
+// line to log, if there's no room for another stack or the owner can't carry the extra weight.
+pub fn move_to_backpack(ecs: &mut World, item: Entity, owner: Entity) -> Result<(), String> {
+    let room = {
+        let backpack = ecs.read_storage::<InBackpack>();
+        let stackable = ecs.read_storage::<Stackable>();
+        let stack = matching_stack(
+            &ecs.entities(),
+            &backpack,
+            &ecs.read_storage::<Name>(),
+            &stackable,
+            &ecs.read_storage::<Malware>(),
+            item,
+            owner,
+        );
+        check_room(
+            &backpack,
+            &stackable,
+            &ecs.read_storage::<Weight>(),
+            ecs.read_storage::<CombatStats>().get(owner),
+            item,
+            owner,
+            stack.is_some(),
+        )
+        .map(|_| stack)
+    };
+    let stack =
+        room.map_err(|no_room| no_room.reason(&identification_system::display_name(ecs, item)))?;
+
+    ecs.write_storage::<Equipped>().remove(item);
+    match stack {
+        Some(stack) => {
+            copy_component::<Malware>(ecs, item, stack);
+            let mut stackable = ecs.write_storage::<Stackable>();
+            let quantity = stackable.get(item).unwrap().quantity;
+            stackable.get_mut(stack).unwrap().quantity += quantity;
+            ecs.entities().delete(item).expect("Delete failed");
+        }
+        None => {
+            ecs.write_storage::<InBackpack>()
+                .insert(item, InBackpack { owner })
+                .expect("Unable to insert backpack entry");
+        }
+    }
+    Ok(())
+}
+
+// Buys one of a vendor's items for the player. Nothing happens if they can't pay or carry it.
+pub fn buy_item(ecs: &mut World, vendor: Entity, item: Entity) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let price = ecs
+        .read_storage::<Value>()
+        .get(item)
+        .map_or(0, |v| v.credits);
+    let name = identification_system::display_name(ecs, item);
+    let wallet = ecs
+        .read_storage::<Credits>()
+        .get(player_entity)
+        .map_or(0, |c| c.amount);
+    if wallet < price {
+        ecs.fetch_mut::<GameLog>()
+            .entries
+            .push(format!("You can't afford the {}.", name));
+        return;
+    }
+
+    let single = split_stack(ecs, item);
+    if let Err(reason) = move_to_backpack(ecs, single, player_entity) {
+        // back on the shelf
+        let _ = move_to_backpack(ecs, single, vendor);
+        ecs.fetch_mut::<GameLog>().entries.push(reason);
+        return;
+    }
+    if let Some(wallet) = ecs.write_storage::<Credits>().get_mut(player_entity) {
+        wallet.amount -= price;
+    }
+    ecs.fetch_mut::<GameLog>()
+        .entries
+        .push(format!("You buy the {} for {} credits.", name, price));
+}
+
+// Sells one of the player's items to a vendor for half its value.
+pub fn sell_item(ecs: &mut World, vendor: Entity, item: Entity) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let name = identification_system::display_name(ecs, item);
+    let price = match ecs.read_storage::<Value>().get(item) {
+        None => {
+            ecs.fetch_mut::<GameLog>()
+                .entries
+                .push(format!("Nobody wants the {}.", name));
+            return;
+        }
+        Some(value) => value.credits / 2,
+    };
+    if is_stuck(ecs, item) {
+        ecs.fetch_mut::<GameLog>()
+            .entries
+            .push(format!("The {} won't come off, it's infected!", name));
+        return;
+    }
+
+    let single = split_stack(ecs, item);
+    ecs.write_storage::<InBackpack>().remove(single);
+    if move_to_backpack(ecs, single, vendor).is_err() {
+        // vendors take everything, however full their shelves are
+        ecs.write_storage::<InBackpack>()
+            .insert(single, InBackpack { owner: vendor })
+            .expect("Unable to insert backpack entry");
+    }
+    if let Some(wallet) = ecs.write_storage::<Credits>().get_mut(player_entity) {
+        wallet.amount += price;
+    }
+    ecs.fetch_mut::<GameLog>()
+        .entries
+        .push(format!("You sell the {} for {} credits.", name, price));
+}
+
+// Takes one of a container's items into the player's backpack.
+pub fn take_item(ecs: &mut World, container: Entity, item: Entity) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let name = identification_system::display_name(ecs, item);
+    let single = split_stack(ecs, item);
+    if let Err(reason) = move_to_backpack(ecs, single, player_entity) {
+        let _ = move_to_backpack(ecs, single, container);
+        ecs.fetch_mut::<GameLog>().entries.push(reason);
+        return;
+    }
+    ecs.fetch_mut::<GameLog>()
+        .entries
+        .push(format!("You take the {}.", name));
+}
+
+// Stows one of the player's items in a container. Containers never fill up.
+pub fn put_item(ecs: &mut World, container: Entity, item: Entity) {
+    let name = identification_system::display_name(ecs, item);
+    if is_stuck(ecs, item) {
+        ecs.fetch_mut::<GameLog>()
+            .entries
+            .push(format!("The {} won't come off, it's infected!", name));
+        return;
+    }
+
+    let single = split_stack(ecs, item);
+    ecs.write_storage::<InBackpack>().remove(single);
+    if move_to_backpack(ecs, single, container).is_err() {
+        ecs.write_storage::<InBackpack>()
+            .insert(single, InBackpack { owner: container })
+            .expect("Unable to insert backpack entry");
+    }
+    ecs.fetch_mut::<GameLog>()
+        .entries
+        .push(format!("You put the {} away.", name));
+}
+
+// Equipped infected gear can't be dropped or thrown either.
+pub fn is_stuck(ecs: &World, item: Entity) -> bool {
+    ecs.read_storage::<Equipped>().get(item).is_some()
+        && ecs.read_storage::<Malware>().get(item).is_some()
+}
 
 pub struct InventorySystem {}
 
@@ -12,17 +312,131 @@ impl<'a> System<'a> for InventorySystem {
     type SystemData = (
         ReadExpect<'a, Entity>,
         WriteExpect<'a, GameLog>,
+        Entities<'a>,
         WriteStorage<'a, WantsToPickupItem>,
         WriteStorage<'a, Position>,
         ReadStorage<'a, Name>,
         WriteStorage<'a, InBackpack>,
+        WriteStorage<'a, Ammo>,
+        WriteStorage<'a, Stackable>,
+        ReadStorage<'a, Weight>,
+        ReadStorage<'a, CombatStats>,
+        ReadExpect<'a, ItemKnowledge>,
+        ReadStorage<'a, ObfuscatedName>,
+        WriteStorage<'a, Malware>,
+        WriteStorage<'a, Credits>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack) =
-            data;
+        let (
+            player_entity,
+            mut gamelog,
+            entities,
+            mut wants_pickup,
+            mut positions,
+            names,
+            mut backpack,
+            mut ammo,
+            mut stackable,
+            weights,
+            combat_stats,
+            knowledge,
+            obfuscated,
+            mut malware,
+            mut credits,
+        ) = data;
 
         for pickup in wants_pickup.join() {
+            // credsticks go straight into the wallet
+            let picked_credits = credits.get(pickup.item).map(|c| c.amount);
+            if let Some(picked_credits) = picked_credits {
+                if let Some(wallet) = credits.get_mut(pickup.collected_by) {
+                    wallet.amount += picked_credits;
+                    if pickup.collected_by == *player_entity {
+                        gamelog
+                            .entries
+                            .push(format!("You pick up {} credits.", picked_credits));
+                    }
+                    entities.delete(pickup.item).expect("Delete failed");
+                }
+                continue;
+            }
+
+            let shown_name = knowledge
+                .display_name(names.get(pickup.item).unwrap(), obfuscated.get(pickup.item));
+
+            // loose rounds go onto the rounds the collector is already carrying, stackable items
+            // onto a stack of the same name
+            let ammo_stack = ammo.get(pickup.item).and_then(|_| {
+                (&entities, &backpack, &ammo)
+                    .join()
+                    .find(|(_, pack, _)| pack.owner == pickup.collected_by)
+                    .map(|(stack, _, _)| stack)
+            });
+            let stack = matching_stack(
+                &entities,
+                &backpack,
+                &names,
+                &stackable,
+                &malware,
+                pickup.item,
+                pickup.collected_by,
+            );
+
+            if let Err(no_room) = check_room(
+                &backpack,
+                &stackable,
+                &weights,
+                combat_stats.get(pickup.collected_by),
+                pickup.item,
+                pickup.collected_by,
+                ammo_stack.is_some() || stack.is_some(),
+            ) {
+                if pickup.collected_by == *player_entity {
+                    gamelog.entries.push(no_room.reason(&shown_name));
+                }
+                continue;
+            }
+
+            if let Some(ammo_stack) = ammo_stack {
+                let picked_rounds = ammo.get(pickup.item).unwrap().rounds;
+                let total = {
+                    let stack_ammo = ammo.get_mut(ammo_stack).unwrap();
+                    stack_ammo.rounds += picked_rounds;
+                    stack_ammo.rounds
+                };
+                if pickup.collected_by == *player_entity {
+                    gamelog.entries.push(format!(
+                        "You pick up {} rounds, {} in total.",
+                        picked_rounds, total
+                    ));
+                }
+                entities.delete(pickup.item).expect("Delete failed");
+                continue;
+            }
+
+            if let Some(stack) = stack {
+                if let Some(infection) = malware.get(pickup.item).cloned() {
+                    malware
+                        .insert(stack, infection)
+                        .expect("Unable to infect stack");
+                }
+                let picked_quantity = stackable.get(pickup.item).unwrap().quantity;
+                let total = {
+                    let stack_quantity = stackable.get_mut(stack).unwrap();
+                    stack_quantity.quantity += picked_quantity;
+                    stack_quantity.quantity
+                };
+                if pickup.collected_by == *player_entity {
+                    gamelog.entries.push(format!(
+                        "You pick up the {}, {} in total.",
+                        shown_name, total
+                    ));
+                }
+                entities.delete(pickup.item).expect("Delete failed");
+                continue;
+            }
+
             positions.remove(pickup.item);
             backpack
                 .insert(
@@ -34,10 +448,9 @@ impl<'a> System<'a> for InventorySystem {
                 .expect("unable to regist entry");
 
             if pickup.collected_by == *player_entity {
-                gamelog.entries.push(format!(
-                    "You pick up the {}",
-                    names.get(pickup.item).unwrap().name
-                ));
+                gamelog
+                    .entries
+                    .push(format!("You pick up the {}", shown_name));
             }
         }
 
@@ -57,6 +470,9 @@ impl<'a> System<'a> for ItemDropSystem {
         ReadStorage<'a, Name>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, InBackpack>,
+        WriteStorage<'a, Equipped>,
+        ReadExpect<'a, ItemKnowledge>,
+        ReadStorage<'a, ObfuscatedName>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -68,6 +484,9 @@ impl<'a> System<'a> for ItemDropSystem {
             names,
             mut positions,
             mut backpack,
+            mut equipped,
+            knowledge,
+            obfuscated,
         ) = data;
 
         for (entity, dropped_item) in (&entities, &dropping_item).join() {
@@ -88,11 +507,16 @@ impl<'a> System<'a> for ItemDropSystem {
                     },
                 )
                 .expect("unable to render position");
+            backpack.remove(dropped_item.item);
+            equipped.remove(dropped_item.item);
 
             if entity == *player_entity {
                 gamelog.entries.push(format!(
                     "You drop the {}.",
-                    names.get(dropped_item.item).unwrap().name
+                    knowledge.display_name(
+                        names.get(dropped_item.item).unwrap(),
+                        obfuscated.get(dropped_item.item)
+                    )
                 ));
             }
         }
@@ -100,6 +524,223 @@ impl<'a> System<'a> for ItemDropSystem {
     }
 }
 
+// How far anything can be thrown, and what it does to whoever it hits.
+pub const THROW_RANGE: i32 = 6;
+const THROW_DAMAGE: DiceType = DiceType {
+    n_dice: 1,
+    die_type: 4,
+    bonus: 0,
+};
+
+pub struct ItemThrowSystem {}
+
+impl<'a> System<'a> for ItemThrowSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, ParticleBuilder>,
+        ReadExpect<'a, Map>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToThrowItem>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, CombatStats>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, InBackpack>,
+        WriteStorage<'a, Equipped>,
+        WriteStorage<'a, SufferDamage>,
+        ReadExpect<'a, ItemKnowledge>,
+        ReadStorage<'a, ObfuscatedName>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            mut rng,
+            mut particle_builder,
+            map,
+            entities,
+            mut wants_throw,
+            names,
+            combat_stats,
+            mut positions,
+            mut backpack,
+            mut equipped,
+            mut suffer_damage,
+            knowledge,
+            obfuscated,
+        ) = data;
+
+        for (entity, throw) in (&entities, &wants_throw).join() {
+            let from = match positions.get(entity) {
+                None => continue,
+                Some(pos) => Point::new(pos.x, pos.y),
+            };
+            let impact = map.projectile_impact(from, throw.target);
+            particle_builder.beam(from, impact, RGB::named(rltk::WHITE), rltk::to_cp437('∙'));
+
+            // whatever was thrown ends up on the floor where it stopped
+            backpack.remove(throw.item);
+            equipped.remove(throw.item);
+            positions
+                .insert(
+                    throw.item,
+                    Position {
+                        x: impact.x,
+                        y: impact.y,
+                    },
+                )
+                .expect("Unable to insert position");
+
+            let item_name =
+                knowledge.display_name(names.get(throw.item).unwrap(), obfuscated.get(throw.item));
+            let idx = map.xy_idx(impact.x, impact.y);
+            let victim = map.tile_content[idx]
+                .iter()
+                .find(|victim| **victim != entity && combat_stats.get(**victim).is_some());
+            match victim {
+                None => {
+                    if entity == *player_entity {
+                        gamelog
+                            .entries
+                            .push(format!("The {} clatters to the floor.", item_name));
+                    }
+                }
+                Some(victim) => {
+                    let damage = rng.roll(THROW_DAMAGE);
+                    SufferDamage::new_damage(
+                        &mut suffer_damage,
+                        *victim,
+                        damage,
+                        DamageType::Kinetic,
+                    );
+                    if entity == *player_entity {
+                        gamelog.entries.push(format!(
+                            "The {} hits the {}, for {} hp.",
+                            item_name,
+                            names.get(*victim).unwrap().name,
+                            damage
+                        ));
+                    }
+                }
+            }
+        }
+
+        wants_throw.clear();
+    }
+}
+
+// Using equipment puts it on, using it again takes it off. Runs before the other item systems and
+// takes the intent away, so they never see equipment.
+pub struct ItemEquipOnUse {}
+
+impl<'a> System<'a> for ItemEquipOnUse {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToUseItem>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Equippable>,
+        WriteStorage<'a, Equipped>,
+        WriteStorage<'a, Malware>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            entities,
+            mut wants_to_use,
+            names,
+            equippable,
+            mut equipped,
+            mut malware,
+        ) = data;
+
+        let mut to_remove: Vec<Entity> = Vec::new();
+        for (entity, useitem) in (&entities, &wants_to_use).join() {
+            if let Some(can_equip) = equippable.get(useitem.item) {
+                let item_name = &names.get(useitem.item).unwrap().name;
+                to_remove.push(entity);
+
+                // infected gear won't let go of its owner
+                let mut stuck: Option<Entity> = None;
+                for (worn_entity, worn, infection) in (&entities, &equipped, &mut malware).join() {
+                    if worn.owner == entity && worn.slot == can_equip.slot {
+                        infection.revealed = true;
+                        stuck = Some(worn_entity);
+                    }
+                }
+                if let Some(stuck) = stuck {
+                    if entity == *player_entity {
+                        gamelog.entries.push(format!(
+                            "The {} won't come off, it's infected!",
+                            names.get(stuck).unwrap().name
+                        ));
+                    }
+                    continue;
+                }
+
+                if equipped.get(useitem.item).is_some() {
+                    equipped.remove(useitem.item);
+                    if entity == *player_entity {
+                        gamelog
+                            .entries
+                            .push(format!("You put away the {}.", item_name));
+                    }
+                    continue;
+                }
+
+                let mut to_unequip: Vec<Entity> = Vec::new();
+                for (worn_entity, worn) in (&entities, &equipped).join() {
+                    if worn.owner == entity && worn.slot == can_equip.slot {
+                        to_unequip.push(worn_entity);
+                    }
+                }
+                for item in to_unequip.iter() {
+                    equipped.remove(*item);
+                    if entity == *player_entity {
+                        gamelog.entries.push(format!(
+                            "You put away the {}.",
+                            names.get(*item).unwrap().name
+                        ));
+                    }
+                }
+                equipped
+                    .insert(
+                        useitem.item,
+                        Equipped {
+                            owner: entity,
+                            slot: can_equip.slot,
+                        },
+                    )
+                    .expect("Unable to equip");
+                if entity == *player_entity {
+                    gamelog
+                        .entries
+                        .push(format!("You equip the {}.", item_name));
+                }
+                if let Some(infection) = malware.get_mut(useitem.item) {
+                    infection.revealed = true;
+                    if entity == *player_entity {
+                        gamelog
+                            .entries
+                            .push(format!("The {} clamps on, it's infected!", item_name));
+                    }
+                }
+            }
+        }
+
+        for entity in to_remove.iter() {
+            wants_to_use.remove(*entity);
+        }
+    }
+}
+
 pub struct UseConsumableSystem {}
 
 impl<'a> System<'a> for UseConsumableSystem {
@@ -118,6 +759,17 @@ impl<'a> System<'a> for UseConsumableSystem {
         ReadStorage<'a, AreaOfEffect>,
         WriteStorage<'a, Disable>,
         WriteStorage<'a, CombatStats>,
+        ReadStorage<'a, Position>,
+        WriteExpect<'a, ParticleBuilder>,
+        ReadStorage<'a, Ability>,
+        ReadStorage<'a, ProvidesCloak>,
+        ReadStorage<'a, ProvidesOverclock>,
+        WriteStorage<'a, Cloaked>,
+        WriteStorage<'a, Overclocked>,
+        WriteStorage<'a, Charges>,
+        ReadStorage<'a, ProvidesRecharge>,
+        ReadStorage<'a, InBackpack>,
+        WriteStorage<'a, Stackable>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -135,21 +787,58 @@ impl<'a> System<'a> for UseConsumableSystem {
             aoe,
             mut inflict_disable,
             mut combat_stats,
+            positions,
+            mut particle_builder,
+            abilities,
+            provides_cloak,
+            provides_overclock,
+            mut cloaked,
+            mut overclocked,
+            mut charges,
+            provides_recharge,
+            backpack,
+            mut stackable,
         ) = data;
 
         for (entity, useitem) in (&entities, &wants_to_use).join() {
+            // a flat device does nothing until it's recharged
+            if charges.get(useitem.item).is_some_and(|c| c.current <= 0) {
+                if entity == *player_entity {
+                    gamelog.entries.push(format!(
+                        "The {} is out of charges.",
+                        names.get(useitem.item).unwrap().name
+                    ));
+                }
+                continue;
+            }
             let mut used_item = true;
             let mut targets: Vec<Entity> = Vec::new();
 
             match useitem.target {
                 None => targets.push(*player_entity),
                 Some(target) => {
+                    let color = inflict_damage
+                        .get(useitem.item)
+                        .map_or(RGB::named(rltk::WHITE), |d| damage_color(d.damage_type));
+                    // projectiles stop at the first thing in their way
+                    let mut target = target;
+                    if let Some(pos) = positions.get(entity) {
+                        let from = Point::new(pos.x, pos.y);
+                        target = map.projectile_impact(from, target);
+                        if inflict_damage.get(useitem.item).is_some()
+                            || inflict_disable.get(useitem.item).is_some()
+                        {
+                            particle_builder.beam(from, target, color, rltk::to_cp437('∙'));
+                        }
+                    }
                     let a_effect = aoe.get(useitem.item);
                     match a_effect {
                         None => {
                             let idx = map.xy_idx(target.x, target.y);
                             for enemies in map.tile_content[idx].iter() {
-                                targets.push(*enemies);
+                                if combat_stats.get(*enemies).is_some() {
+                                    targets.push(*enemies);
+                                }
                             }
                         }
                         Some(a_effect) => {
@@ -162,9 +851,24 @@ impl<'a> System<'a> for UseConsumableSystem {
 
                             for tile_index in affected_tiles.iter() {
                                 let idx = map.xy_idx(tile_index.x, tile_index.y);
+                                particle_builder.request(
+                                    tile_index.x,
+                                    tile_index.y,
+                                    color,
+                                    RGB::named(rltk::BLACK),
+                                    rltk::to_cp437('░'),
+                                    BLAST_MS,
+                                );
 
                                 for enemies in map.tile_content[idx].iter() {
-                                    targets.push(*enemies)
+                                    // an ability's pulse doesn't hurt whoever set it off
+                                    if *enemies == entity && abilities.get(useitem.item).is_some() {
+                                        continue;
+                                    }
+                                    // skip lamps, items and anything else that can't be hurt
+                                    if combat_stats.get(*enemies).is_some() {
+                                        targets.push(*enemies)
+                                    }
                                 }
                             }
                         }
@@ -200,7 +904,12 @@ impl<'a> System<'a> for UseConsumableSystem {
                 Some(damage) => {
                     used_item = false;
                     for enemy in targets.iter() {
-                        SufferDamage::new_damage(&mut suffer_damage, *enemy, damage.damage);
+                        SufferDamage::new_damage(
+                            &mut suffer_damage,
+                            *enemy,
+                            damage.damage,
+                            damage.damage_type,
+                        );
                         if entity == *player_entity {
                             let enemy_name = names.get(*enemy).unwrap();
                             let item_name = names.get(useitem.item).unwrap();
@@ -242,6 +951,7 @@ impl<'a> System<'a> for UseConsumableSystem {
 
                         for enemy in targets.iter() {
                             disable_affected.push((*enemy, disabling.turns));
+                            used_item = true;
 
                             if entity == *player_entity {
                                 let enemy_name = names.get(*enemy).unwrap();
@@ -261,11 +971,80 @@ impl<'a> System<'a> for UseConsumableSystem {
                     .insert(enemy.0, Disable { turns: enemy.1 })
                     .expect("Unable to inflic disable");
             }
+
+            if let Some(cloak) = provides_cloak.get(useitem.item) {
+                for target in targets.iter() {
+                    cloaked
+                        .insert(*target, Cloaked { turns: cloak.turns })
+                        .expect("Unable to insert cloak");
+                }
+                if entity == *player_entity {
+                    gamelog
+                        .entries
+                        .push("You shimmer out of sight.".to_string());
+                }
+            }
+
+            if let Some(overclock) = provides_overclock.get(useitem.item) {
+                for target in targets.iter() {
+                    overclocked
+                        .insert(
+                            *target,
+                            Overclocked {
+                                turns: overclock.turns,
+                            },
+                        )
+                        .expect("Unable to insert overclock");
+                }
+                if entity == *player_entity {
+                    gamelog
+                        .entries
+                        .push("Your cyberware overclocks.".to_string());
+                }
+            }
+            if provides_recharge.get(useitem.item).is_some() {
+                let mut recharged = 0;
+                for (device, device_charges, pack) in (&entities, &mut charges, &backpack).join() {
+                    if pack.owner == entity
+                        && device != useitem.item
+                        && device_charges.current < device_charges.max
+                    {
+                        device_charges.current = device_charges.max;
+                        recharged += 1;
+                    }
+                }
+                if recharged == 0 {
+                    used_item = false;
+                }
+                if entity == *player_entity {
+                    if recharged == 0 {
+                        gamelog
+                            .entries
+                            .push("Nothing you carry needs recharging.".to_string());
+                    } else {
+                        gamelog
+                            .entries
+                            .push(format!("You recharge {} device(s).", recharged));
+                    }
+                }
+            }
+
+            // charged devices spend a charge, and only run out once they're empty
+            let mut spent = used_item;
+            if let Some(device_charges) = charges.get_mut(useitem.item) {
+                if used_item {
+                    device_charges.current -= 1;
+                }
+                spent = used_item && device_charges.current <= 0;
+            }
+
             // if consumeable, then delete.
             let consumeable = consumables.get(useitem.item);
             match consumeable {
-                None => {}
-                Some(_) => {
+                Some(_) if spent && stackable.get(useitem.item).is_some_and(|s| s.quantity > 1) => {
+                    stackable.get_mut(useitem.item).unwrap().quantity -= 1;
+                }
+                Some(_) if spent => {
                     // stats.hp = i32::min(stats.max_hp, stats.hp + potion.heal_amount);
                     // if entity == *player_entity {
                     //     gamelog.entries.push(format!(
@@ -276,9 +1055,165 @@ impl<'a> System<'a> for UseConsumableSystem {
                     // }
                     entities.delete(useitem.item).expect("Delete failed");
                 }
+                _ => {}
             }
         }
 
         wants_to_use.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_world, Keycard};
+
+    // Two of an item in one backpack stack.
+    fn stack_of_two(ecs: &mut World, owner: Entity, name: &str) -> Entity {
+        for _ in 0..2 {
+            let item = spawner::named_item(ecs, 0, 0, name).unwrap();
+            ecs.write_storage::<Position>().remove(item);
+            ecs.write_storage::<Malware>().remove(item);
+            assert!(move_to_backpack(ecs, item, owner).is_ok());
+        }
+        ecs.maintain();
+        let backpack = ecs.read_storage::<InBackpack>();
+        (&ecs.entities(), &backpack)
+            .join()
+            .find(|(_, pack)| pack.owner == owner)
+            .map(|(stack, _)| stack)
+            .unwrap()
+    }
+
+    const STACKABLES: [&str; 5] = [
+        "Volt Pack(HP)",
+        "Keycard",
+        "Antivirus",
+        "EMP Chip",
+        "Capacitor",
+    ];
+
+    #[test]
+    fn split_item_is_a_whole_item_of_its_kind() {
+        for name in STACKABLES.iter() {
+            let mut ecs = test_world(7);
+            let owner = ecs.create_entity().build();
+            let stack = stack_of_two(&mut ecs, owner, name);
+
+            let single = split_stack(&mut ecs, stack);
+            assert_ne!(single, stack);
+
+            let stackable = ecs.read_storage::<Stackable>();
+            assert_eq!(stackable.get(stack).unwrap().quantity, 1);
+            assert_eq!(stackable.get(single).unwrap().quantity, 1);
+            assert_eq!(ecs.read_storage::<Name>().get(single).unwrap().name, *name);
+            assert_eq!(
+                ecs.read_storage::<InBackpack>().get(single).unwrap().owner,
+                owner
+            );
+            assert!(ecs.read_storage::<Position>().get(single).is_none());
+            assert_eq!(
+                ecs.read_storage::<Keycard>().get(single).is_some(),
+                ecs.read_storage::<Keycard>().get(stack).is_some()
+            );
+        }
+    }
+
+    #[test]
+    fn split_item_shares_the_stacks_infection() {
+        for infected in [false, true].iter() {
+            let mut ecs = test_world(7);
+            let owner = ecs.create_entity().build();
+            let stack = stack_of_two(&mut ecs, owner, "EMP Chip");
+            if *infected {
+                ecs.write_storage::<Malware>()
+                    .insert(stack, Malware { revealed: false })
+                    .unwrap();
+            }
+
+            let single = split_stack(&mut ecs, stack);
+            assert_eq!(
+                ecs.read_storage::<Malware>().get(single).is_some(),
+                *infected
+            );
+        }
+    }
+
+    #[test]
+    fn hidden_malware_shares_a_stack_until_found() {
+        let mut ecs = test_world(7);
+        let owner = ecs.create_entity().build();
+        let stack = stack_of_two(&mut ecs, owner, "EMP Chip");
+        let infected = spawner::named_item(&mut ecs, 0, 0, "EMP Chip").unwrap();
+        ecs.write_storage::<Malware>()
+            .insert(infected, Malware { revealed: false })
+            .unwrap();
+        assert!(move_to_backpack(&mut ecs, infected, owner).is_ok());
+        ecs.maintain();
+
+        let entries = |ecs: &World| {
+            ecs.read_storage::<InBackpack>()
+                .join()
+                .filter(|pack| pack.owner == owner)
+                .count()
+        };
+        assert_eq!(entries(&ecs), 1);
+        assert!(ecs.read_storage::<Malware>().get(stack).is_some());
+
+        let found = spawner::named_item(&mut ecs, 0, 0, "EMP Chip").unwrap();
+        ecs.write_storage::<Malware>()
+            .insert(found, Malware { revealed: true })
+            .unwrap();
+        assert!(move_to_backpack(&mut ecs, found, owner).is_ok());
+        assert_eq!(entries(&ecs), 2);
+    }
+
+    #[test]
+    fn splitting_leaves_the_random_numbers_alone() {
+        let mut ecs = test_world(7);
+        let owner = ecs.create_entity().build();
+        let stack = stack_of_two(&mut ecs, owner, "EMP Chip");
+        let mut untouched = test_world(7);
+        stack_of_two(&mut untouched, owner, "EMP Chip");
+
+        split_stack(&mut ecs, stack);
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let mut untouched_rng = untouched.write_resource::<RandomNumberGenerator>();
+        assert_eq!(rng.next_u64(), untouched_rng.next_u64());
+    }
+
+    #[test]
+    fn unknown_stack_comes_back_unsplit() {
+        let mut ecs = test_world(7);
+        let stack = ecs
+            .create_entity()
+            .with(Name {
+                name: "Mystery Box".to_string(),
+            })
+            .with(Stackable { quantity: 2 })
+            .build();
+
+        assert_eq!(split_stack(&mut ecs, stack), stack);
+        assert_eq!(
+            ecs.read_storage::<Stackable>().get(stack).unwrap().quantity,
+            2
+        );
+    }
+
+    #[test]
+    fn nothing_goes_into_a_backpack_past_the_weight_cap() {
+        let mut ecs = test_world(7);
+        let owner = spawner::player(&mut ecs, 0, 0);
+        let cap = carry_capacity(ecs.read_storage::<CombatStats>().get(owner).unwrap()) * HARD_CAP;
+
+        let mut carried = 0;
+        let weight = loop {
+            let item = spawner::named_item(&mut ecs, 0, 0, "Barrier Emitter").unwrap();
+            if move_to_backpack(&mut ecs, item, owner).is_err() {
+                break ecs.read_storage::<Weight>().get(item).unwrap().weight;
+            }
+            carried += 1;
+        };
+        assert_eq!(carried, (cap / weight) as i32);
+    }
+}
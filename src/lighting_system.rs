@@ -0,0 +1,36 @@
+use super::{LightSource, Map, Position};
+use rltk::{field_of_view, Point, RGB};
+use specs::prelude::*;
+
+pub struct LightingSystem {}
+
+impl<'a> System<'a> for LightingSystem {
+    type SystemData = (
+        WriteExpect<'a, Map>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, LightSource>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut map, positions, lights) = data;
+
+        let tile_count = map.tiles.len();
+        map.light.clear();
+        map.light.resize(tile_count, RGB::from_f32(0.0, 0.0, 0.0));
+
+        for (pos, light) in (&positions, &lights).join() {
+            let lit_tiles = field_of_view(Point::new(pos.x, pos.y), light.range, &*map);
+            for tile in lit_tiles.iter() {
+                if tile.x < 0 || tile.x >= map.width || tile.y < 0 || tile.y >= map.height {
+                    continue;
+                }
+                let distance =
+                    rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *tile);
+                // fades out towards the edge of the light's range
+                let intensity = f32::max(0.0, 1.0 - (distance / (light.range + 1) as f32));
+                let idx = map.xy_idx(tile.x, tile.y);
+                map.light[idx] = map.light[idx] + (light.color * intensity);
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use super::{
+    gamelog::GameLog, CombatStats, InBackpack, Keycard, Locked, Name, Stackable, WantsToUnlock,
+};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+// A keycard always works and is used up. Without one the player tries to hack the lock, rolling
+// d20 plus their accuracy against its difficulty.
+pub struct UnlockSystem {}
+
+impl<'a> System<'a> for UnlockSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteStorage<'a, WantsToUnlock>,
+        WriteStorage<'a, Locked>,
+        ReadStorage<'a, Keycard>,
+        ReadStorage<'a, InBackpack>,
+        WriteStorage<'a, Stackable>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Name>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut gamelog,
+            mut rng,
+            mut wants_unlock,
+            mut locked,
+            keycards,
+            backpack,
+            mut stackable,
+            combat_stats,
+            names,
+        ) = data;
+
+        for (entity, unlock) in (&entities, &wants_unlock).join() {
+            let difficulty = match locked.get(unlock.container) {
+                None => continue,
+                Some(lock) => lock.difficulty,
+            };
+            let container_name = &names.get(unlock.container).unwrap().name;
+
+            let keycard = (&entities, &keycards, &backpack)
+                .join()
+                .find(|(_, _, pack)| pack.owner == entity)
+                .map(|(card, _, _)| card);
+            if let Some(card) = keycard {
+                match stackable.get_mut(card) {
+                    Some(stack) if stack.quantity > 1 => stack.quantity -= 1,
+                    _ => entities.delete(card).expect("Delete failed"),
+                }
+                locked.remove(unlock.container);
+                gamelog.entries.push(format!(
+                    "You swipe a keycard and the {} clicks open.",
+                    container_name
+                ));
+                continue;
+            }
+
+            let skill = combat_stats.get(entity).map_or(0, |stats| stats.accuracy);
+            if rng.roll_dice(1, 20) + skill >= difficulty {
+                locked.remove(unlock.container);
+                gamelog
+                    .entries
+                    .push(format!("You hack the {}'s lock.", container_name));
+            } else {
+                gamelog
+                    .entries
+                    .push(format!("The {}'s lock holds.", container_name));
+            }
+        }
+
+        wants_unlock.clear();
+    }
+}
@@ -15,9 +15,15 @@ mod gui;
 mod rect;
 mod spawner;
 pub use rect::Rect;
+mod auto_move;
+mod cli;
+mod crafting;
 mod gamelog;
+mod map_builders;
 
 // SYSTEMS
+mod lighting_system;
+use lighting_system::LightingSystem;
 mod visibility_system;
 use visibility_system::VisibilitySystem;
 mod monster_ai_system;
@@ -26,15 +32,34 @@ mod map_indexing_system;
 use map_indexing_system::MapIndexingSystem;
 mod melee_combat_system;
 use melee_combat_system::MeleeCombatSystem;
+mod ranged_combat_system;
+use ranged_combat_system::{ReloadSystem, ShootingSystem};
 mod damage_system;
 use damage_system::DamageSystem;
+mod shield_system;
+use shield_system::ShieldSystem;
+mod ability_system;
+use ability_system::{AbilityUseSystem, EnergySystem};
 mod inventory_system;
-use inventory_system::{InventorySystem, ItemDropSystem, UseConsumableSystem};
+use inventory_system::{
+    InventorySystem, ItemDropSystem, ItemEquipOnUse, ItemThrowSystem, UseConsumableSystem,
+};
+mod encumbrance_system;
+use encumbrance_system::EncumbranceSystem;
+mod identification_system;
+use identification_system::IdentificationSystem;
+mod implant_system;
+use implant_system::ImplantSystem;
+mod malware_system;
+use malware_system::MalwareSystem;
+mod lock_system;
+use lock_system::UnlockSystem;
+mod particle_system;
+use particle_system::ParticleSpawnSystem;
 
 pub mod saveload_system;
 
-mod components;
-
+const MAPGEN_FRAME_TIME: f32 = 200.0;
 
 #[derive(PartialEq, Copy, Clone)]
 pub enum RunState {
@@ -44,22 +69,48 @@ pub enum RunState {
     MonsterTurn,
     ShowInventory,
     ShowDropItem,
+    ShowThrowItem,
     ShowTargeting {
         range: i32,
         item: Entity,
     },
+    ThrowTargeting {
+        item: Entity,
+    },
     MainMenu {
         menu_selection: gui::MainMenuSelection,
     },
     SaveGame,
+    MapGeneration,
+    NextLevel,
+    Looking,
+    ShowVendor {
+        vendor: Entity,
+        mode: gui::VendorMode,
+    },
+    ShowCrafting,
+    ShowPickup,
+    ShowContainer {
+        container: Entity,
+        mode: gui::ContainerMode,
+    },
 }
 
 pub struct State {
     pub ecs: World,
+    mapgen_visualizer: bool,
+    mapgen_history: Vec<Map>,
+    mapgen_index: usize,
+    mapgen_timer: f32,
+    // keyboard cursor while targeting or looking, None otherwise
+    cursor: Option<Point>,
 }
 
 impl State {
     fn run_systems(&mut self) {
+        let mut lighting = LightingSystem {};
+        lighting.run_now(&self.ecs);
+
         let mut vis = VisibilitySystem {};
         vis.run_now(&self.ecs);
 
@@ -72,20 +123,165 @@ impl State {
         let mut melee = MeleeCombatSystem {};
         melee.run_now(&self.ecs);
 
+        let mut unlock = UnlockSystem {};
+        unlock.run_now(&self.ecs);
+
+        let mut shooting = ShootingSystem {};
+        shooting.run_now(&self.ecs);
+
+        let mut reload = ReloadSystem {};
+        reload.run_now(&self.ecs);
+
         let mut damage = DamageSystem {};
         damage.run_now(&self.ecs);
 
+        let mut shields = ShieldSystem {};
+        shields.run_now(&self.ecs);
+
+        let mut energy = EnergySystem {};
+        energy.run_now(&self.ecs);
+
         let mut pickup = InventorySystem {};
         pickup.run_now(&self.ecs);
 
+        let mut malware = MalwareSystem {};
+        malware.run_now(&self.ecs);
+
+        let mut equip = ItemEquipOnUse {};
+        equip.run_now(&self.ecs);
+
+        let mut implants = ImplantSystem {};
+        implants.run_now(&self.ecs);
+
+        let mut abilities = AbilityUseSystem {};
+        abilities.run_now(&self.ecs);
+
+        let mut identify = IdentificationSystem {};
+        identify.run_now(&self.ecs);
+
         let mut volt_packs = UseConsumableSystem {};
         volt_packs.run_now(&self.ecs);
 
         let mut drop_items = ItemDropSystem {};
         drop_items.run_now(&self.ecs);
 
+        let mut throw_items = ItemThrowSystem {};
+        throw_items.run_now(&self.ecs);
+
+        let mut encumbrance = EncumbranceSystem {};
+        encumbrance.run_now(&self.ecs);
+
+        let mut particles = ParticleSpawnSystem {};
+        particles.run_now(&self.ecs);
+
         self.ecs.maintain(); // MUST BE AT BOTTOM
     }
+
+    // Everything that doesn't come along when the player takes the stairs.
+    fn entities_to_remove_on_level_change(&mut self) -> Vec<Entity> {
+        let entities = self.ecs.entities();
+        let player = self.ecs.read_storage::<Player>();
+        let backpack = self.ecs.read_storage::<InBackpack>();
+        let abilities = self.ecs.read_storage::<Ability>();
+        let installed = self.ecs.read_storage::<Installed>();
+        let player_entity = self.ecs.fetch::<Entity>();
+
+        let mut to_delete: Vec<Entity> = Vec::new();
+        for entity in entities.join() {
+            if player.get(entity).is_some() {
+                continue;
+            }
+            if let Some(pack) = backpack.get(entity) {
+                if pack.owner == *player_entity {
+                    continue;
+                }
+            }
+            if let Some(ability) = abilities.get(entity) {
+                if ability.owner == *player_entity {
+                    continue;
+                }
+            }
+            if let Some(implant) = installed.get(entity) {
+                if implant.owner == *player_entity {
+                    continue;
+                }
+            }
+            to_delete.push(entity);
+        }
+        to_delete
+    }
+
+    // Infected gear that's equipped stays with the player.
+    fn stuck_item(&mut self, item: Entity) -> bool {
+        if !inventory_system::is_stuck(&self.ecs, item) {
+            return false;
+        }
+        if let Some(infection) = self.ecs.write_storage::<Malware>().get_mut(item) {
+            infection.revealed = true;
+        }
+        let names = self.ecs.read_storage::<Name>();
+        let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
+        gamelog.entries.push(format!(
+            "The {} won't come off, it's infected!",
+            names.get(item).unwrap().name
+        ));
+        true
+    }
+
+    fn goto_next_level(&mut self) {
+        let to_delete = self.entities_to_remove_on_level_change();
+        for target in to_delete {
+            self.ecs
+                .delete_entity(target)
+                .expect("Unable to delete entity");
+        }
+
+        let current_depth = self.ecs.fetch::<Map>().depth;
+        self.generate_world_map(current_depth + 1);
+
+        let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
+        gamelog.entries.push(format!(
+            "You ride the service lift down to sublevel {}.",
+            current_depth + 1
+        ));
+    }
+
+    // Builds a fresh map, moves the player to its start and populates it.
+    fn generate_world_map(&mut self, new_depth: i32) {
+        self.mapgen_index = 0;
+        self.mapgen_timer = 0.0;
+
+        let mut builder;
+        {
+            let mut rng = self.ecs.write_resource::<rltk::RandomNumberGenerator>();
+            builder = map_builders::random_builder(new_depth, &mut rng);
+            builder.build_map(&mut rng);
+        }
+        self.mapgen_history = builder.get_snapshot_history();
+        {
+            let mut worldmap = self.ecs.write_resource::<Map>();
+            *worldmap = builder.get_map();
+        }
+
+        // place the player before spawning, so nothing lands on top of them
+        let start = builder.get_starting_position();
+        {
+            let mut player_position = self.ecs.write_resource::<Point>();
+            *player_position = Point::new(start.x, start.y);
+            let player_entity = self.ecs.fetch::<Entity>();
+            let mut positions = self.ecs.write_storage::<Position>();
+            if let Some(pos) = positions.get_mut(*player_entity) {
+                *pos = start.clone();
+            }
+            let mut viewsheds = self.ecs.write_storage::<Viewshed>();
+            if let Some(viewshed) = viewsheds.get_mut(*player_entity) {
+                viewshed.dirty = true;
+            }
+        }
+
+        builder.spawn_entities(&mut self.ecs);
+        spawner::vendor(&mut self.ecs);
+    }
 }
 
 // RENDER LOOP
@@ -98,11 +294,12 @@ impl GameState for State {
             // note - access data within mutable reference, instead of the ref itself.
         }
         ctx.cls();
+        particle_system::cull_dead_particles(&mut self.ecs, ctx);
 
         match new_run_state {
-            RunState::MainMenu { .. } => {}
+            RunState::MainMenu { .. } | RunState::MapGeneration => {}
             _ => {
-                draw_map(&self.ecs, ctx);
+                draw_map(&self.ecs.fetch::<Map>(), ctx);
 
                 {
                     let positions = self.ecs.read_storage::<Position>();
@@ -110,7 +307,7 @@ impl GameState for State {
                     let map = self.ecs.fetch::<Map>();
 
                     let mut data = (&positions, &renderables).join().collect::<Vec<_>>();
-                    data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order));
+                    data.sort_by_key(|&(_, render)| std::cmp::Reverse(render.render_order));
                     for (pos, render) in data.iter() {
                         let idx = map.xy_idx(pos.x, pos.y);
                         if map.visible_tiles[idx] {
@@ -124,23 +321,60 @@ impl GameState for State {
         }
 
         match new_run_state {
+            RunState::MapGeneration => {
+                if self.mapgen_index < self.mapgen_history.len() {
+                    draw_map(&self.mapgen_history[self.mapgen_index], ctx);
+                }
+
+                self.mapgen_timer += ctx.frame_time_ms;
+                if self.mapgen_timer > MAPGEN_FRAME_TIME {
+                    self.mapgen_timer = 0.0;
+                    self.mapgen_index += 1;
+                }
+                // any key skips the rest of the playback
+                if self.mapgen_index >= self.mapgen_history.len() || ctx.key.is_some() {
+                    new_run_state = RunState::PreRun;
+                }
+            }
             RunState::PreRun => {
                 self.run_systems();
                 self.ecs.maintain();
                 new_run_state = RunState::AwaitingInput;
             }
             RunState::AwaitingInput => {
-                new_run_state = player_input(self, ctx);
+                if player::locked_up(&mut self.ecs) {
+                    new_run_state = RunState::PlayerTurn;
+                } else if auto_move::is_active(&self.ecs) {
+                    new_run_state = auto_move::step(&mut self.ecs, ctx);
+                } else {
+                    new_run_state = player_input(self, ctx);
+                }
             }
             RunState::PlayerTurn => {
                 self.run_systems();
                 self.ecs.maintain();
                 new_run_state = RunState::MonsterTurn;
+                // the mirror of encumbrance: a quick player now and then acts twice in a row
+                let player_entity = *self.ecs.fetch::<Entity>();
+                if let Some(speed) = self.ecs.write_storage::<Speed>().get_mut(player_entity) {
+                    speed.banked += speed.speed;
+                    if speed.banked >= implant_system::TURN_COST {
+                        speed.banked -= implant_system::TURN_COST;
+                        new_run_state = RunState::AwaitingInput;
+                    }
+                }
             }
             RunState::MonsterTurn => {
                 self.run_systems();
                 self.ecs.maintain();
-                new_run_state = RunState::AwaitingInput
+                new_run_state = RunState::AwaitingInput;
+                // carrying too much hands the world an extra turn now and then
+                let player_entity = *self.ecs.fetch::<Entity>();
+                if let Some(encumbered) = self.ecs.read_storage::<Encumbered>().get(player_entity) {
+                    if encumbered.turns % encumbrance_system::SLOW_EVERY == 0 {
+                        new_run_state = RunState::MonsterTurn;
+                    }
+                }
             }
             RunState::ShowInventory => {
                 let result = gui::show_inventory(self, ctx);
@@ -152,8 +386,21 @@ impl GameState for State {
 
                         let is_ranged = self.ecs.read_storage::<Ranged>();
                         let is_ranged_item = is_ranged.get(item_entity);
-
-                        if let Some(is_ranged_item) = is_ranged_item {
+                        let is_flat = self
+                            .ecs
+                            .read_storage::<Charges>()
+                            .get(item_entity)
+                            .is_some_and(|c| c.current <= 0);
+
+                        // a flat device never gets as far as targeting, and doesn't cost a turn
+                        if is_flat {
+                            let name = identification_system::display_name(&self.ecs, item_entity);
+                            let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
+                            gamelog
+                                .entries
+                                .push(format!("The {} is out of charges.", name));
+                            new_run_state = RunState::AwaitingInput;
+                        } else if let Some(is_ranged_item) = is_ranged_item {
                             new_run_state = RunState::ShowTargeting {
                                 range: is_ranged_item.range,
                                 item: item_entity,
@@ -181,13 +428,64 @@ impl GameState for State {
                     gui::ItemMenuResult::Cancel => new_run_state = RunState::AwaitingInput,
                     gui::ItemMenuResult::NoResponse => {}
                     gui::ItemMenuResult::Selected => {
-                        let mut intent = self.ecs.write_storage::<WantsToUseItem>();
+                        let player_entity = *self.ecs.fetch::<Entity>();
+                        // guns are fired, everything else goes through the item system
+                        if self.ecs.read_storage::<Gun>().get(item).is_some() {
+                            let mut intent = self.ecs.write_storage::<WantsToShoot>();
+                            intent
+                                .insert(
+                                    player_entity,
+                                    WantsToShoot {
+                                        target: result.1.unwrap(),
+                                    },
+                                )
+                                .expect("unable to insert intent");
+                        } else {
+                            let mut intent = self.ecs.write_storage::<WantsToUseItem>();
+                            intent
+                                .insert(
+                                    player_entity,
+                                    WantsToUseItem {
+                                        item,
+                                        target: result.1,
+                                    },
+                                )
+                                .expect("unable to insert intent");
+                        }
+                        new_run_state = RunState::PlayerTurn;
+                    }
+                }
+            }
+            RunState::ShowThrowItem => {
+                let result = gui::throw_item_menu(self, ctx);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => new_run_state = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => {
+                        let item = result.1.unwrap();
+                        if self.stuck_item(item) {
+                            new_run_state = RunState::AwaitingInput;
+                        } else {
+                            new_run_state = RunState::ThrowTargeting { item };
+                        }
+                    }
+                }
+            }
+            RunState::ThrowTargeting { item } => {
+                let result = gui::ranged_target(self, ctx, inventory_system::THROW_RANGE);
+
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => new_run_state = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => {
+                        let item = inventory_system::split_stack(&mut self.ecs, item);
+                        let mut intent = self.ecs.write_storage::<WantsToThrowItem>();
                         intent
                             .insert(
                                 *self.ecs.fetch::<Entity>(),
-                                WantsToUseItem {
+                                WantsToThrowItem {
                                     item,
-                                    target: result.1,
+                                    target: result.1.unwrap(),
                                 },
                             )
                             .expect("unable to insert intent");
@@ -195,6 +493,80 @@ impl GameState for State {
                     }
                 }
             }
+            RunState::ShowVendor { vendor, mode } => {
+                let result = gui::show_vendor(self, ctx, vendor, mode);
+                match result.0 {
+                    gui::VendorResult::Cancel => new_run_state = RunState::AwaitingInput,
+                    gui::VendorResult::NoResponse => {}
+                    gui::VendorResult::SwitchMode => {
+                        let mode = match mode {
+                            gui::VendorMode::Buy => gui::VendorMode::Sell,
+                            gui::VendorMode::Sell => gui::VendorMode::Buy,
+                        };
+                        new_run_state = RunState::ShowVendor { vendor, mode };
+                    }
+                    gui::VendorResult::Buy => {
+                        inventory_system::buy_item(&mut self.ecs, vendor, result.1.unwrap());
+                        self.ecs.maintain();
+                    }
+                    gui::VendorResult::Sell => {
+                        inventory_system::sell_item(&mut self.ecs, vendor, result.1.unwrap());
+                        self.ecs.maintain();
+                    }
+                }
+            }
+            RunState::ShowContainer { container, mode } => {
+                let result = gui::show_container(self, ctx, container, mode);
+                match result.0 {
+                    gui::ContainerResult::Cancel => new_run_state = RunState::AwaitingInput,
+                    gui::ContainerResult::NoResponse => {}
+                    gui::ContainerResult::SwitchMode => {
+                        let mode = match mode {
+                            gui::ContainerMode::Take => gui::ContainerMode::Put,
+                            gui::ContainerMode::Put => gui::ContainerMode::Take,
+                        };
+                        new_run_state = RunState::ShowContainer { container, mode };
+                    }
+                    gui::ContainerResult::Take => {
+                        inventory_system::take_item(&mut self.ecs, container, result.1.unwrap());
+                        self.ecs.maintain();
+                    }
+                    gui::ContainerResult::Put => {
+                        inventory_system::put_item(&mut self.ecs, container, result.1.unwrap());
+                        self.ecs.maintain();
+                    }
+                }
+            }
+            RunState::ShowPickup => {
+                let result = gui::show_pickup(self, ctx);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => new_run_state = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => {
+                        player::pick_up(&mut self.ecs, &result.1);
+                        new_run_state = RunState::PlayerTurn;
+                    }
+                }
+            }
+            RunState::ShowCrafting => {
+                let result = gui::show_crafting(self, ctx);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => new_run_state = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => {
+                        let recipe = self.ecs.fetch::<crafting::Recipes>().recipes
+                            [result.1.unwrap()]
+                        .clone();
+                        crafting::craft(&mut self.ecs, &recipe);
+                        self.ecs.maintain();
+                    }
+                }
+            }
+            RunState::Looking => {
+                if gui::look(self, ctx) == gui::ItemMenuResult::Cancel {
+                    new_run_state = RunState::AwaitingInput;
+                }
+            }
             RunState::ShowDropItem => {
                 //
                 let result = gui::drop_item_menu(self, ctx);
@@ -202,15 +574,20 @@ impl GameState for State {
                     gui::ItemMenuResult::Cancel => new_run_state = RunState::AwaitingInput,
                     gui::ItemMenuResult::NoResponse => {}
                     gui::ItemMenuResult::Selected => {
-                        let item_entity = result.1.unwrap();
-                        let mut intent = self.ecs.write_storage::<WantsToDropItem>();
-                        intent
-                            .insert(
-                                *self.ecs.fetch::<Entity>(),
-                                WantsToDropItem { item: item_entity },
-                            )
-                            .expect("Unable to drop item");
-                        new_run_state = RunState::PlayerTurn;
+                        if self.stuck_item(result.1.unwrap()) {
+                            new_run_state = RunState::AwaitingInput;
+                        } else {
+                            let item_entity =
+                                inventory_system::split_stack(&mut self.ecs, result.1.unwrap());
+                            let mut intent = self.ecs.write_storage::<WantsToDropItem>();
+                            intent
+                                .insert(
+                                    *self.ecs.fetch::<Entity>(),
+                                    WantsToDropItem { item: item_entity },
+                                )
+                                .expect("Unable to drop item");
+                            new_run_state = RunState::PlayerTurn;
+                        }
                     }
                 }
             }
@@ -224,10 +601,17 @@ impl GameState for State {
                         }
                     }
                     gui::MainMenuResult::Selected { selected } => match selected {
-                        gui::MainMenuSelection::NewGame => new_run_state = RunState::PreRun,
+                        gui::MainMenuSelection::NewGame => {
+                            if self.mapgen_visualizer {
+                                new_run_state = RunState::MapGeneration;
+                            } else {
+                                new_run_state = RunState::PreRun;
+                            }
+                        }
                         gui::MainMenuSelection::LoadGame => {
                             saveload_system::load_game(&mut self.ecs);
-                            new_run_state = RunState::AwaitingInput;
+                            // light isn't saved, run the systems once to recalculate it
+                            new_run_state = RunState::PreRun;
                             saveload_system::delete_save();
                         }
                         gui::MainMenuSelection::Quit => {
@@ -236,6 +620,14 @@ impl GameState for State {
                     },
                 }
             }
+            RunState::NextLevel => {
+                self.goto_next_level();
+                if self.mapgen_visualizer {
+                    new_run_state = RunState::MapGeneration;
+                } else {
+                    new_run_state = RunState::PreRun;
+                }
+            }
             RunState::SaveGame => {
                 saveload_system::save_game(&mut self.ecs);
                 new_run_state = RunState::MainMenu {
@@ -252,60 +644,140 @@ impl GameState for State {
     }
 }
 
+// Every component has to be registered before anything can be spawned with it.
+fn register_components(ecs: &mut World) {
+    ecs.register::<Position>();
+    ecs.register::<Renderable>();
+    ecs.register::<Player>();
+    ecs.register::<Viewshed>();
+    ecs.register::<Resistances>();
+    ecs.register::<LightSource>();
+    ecs.register::<Monster>();
+    ecs.register::<Name>();
+    ecs.register::<BlocksTile>();
+    ecs.register::<CombatStats>();
+    ecs.register::<Shield>();
+    ecs.register::<ShieldBonus>();
+    ecs.register::<Energy>();
+    ecs.register::<Ability>();
+    ecs.register::<Dash>();
+    ecs.register::<ProvidesCloak>();
+    ecs.register::<ProvidesOverclock>();
+    ecs.register::<Cloaked>();
+    ecs.register::<Overclocked>();
+    ecs.register::<WantsToMelee>();
+    ecs.register::<SufferDamage>();
+    ecs.register::<Item>();
+    ecs.register::<ProvidesHealing>();
+    ecs.register::<InflictsDamage>();
+    ecs.register::<AreaOfEffect>();
+    ecs.register::<Consumeable>();
+    ecs.register::<Charges>();
+    ecs.register::<Stackable>();
+    ecs.register::<Weight>();
+    ecs.register::<ObfuscatedName>();
+    ecs.register::<ProvidesIdentification>();
+    ecs.register::<Malware>();
+    ecs.register::<ProvidesAntivirus>();
+    ecs.register::<Credits>();
+    ecs.register::<Value>();
+    ecs.register::<LootTable>();
+    ecs.register::<Vendor>();
+    ecs.register::<Container>();
+    ecs.register::<Locked>();
+    ecs.register::<Keycard>();
+    ecs.register::<WantsToUnlock>();
+    ecs.register::<Encumbered>();
+    ecs.register::<Speed>();
+    ecs.register::<Implant>();
+    ecs.register::<Installed>();
+    ecs.register::<Humanity>();
+    ecs.register::<ImplantBonus>();
+    ecs.register::<GrantsAbility>();
+    ecs.register::<ProvidesRecharge>();
+    ecs.register::<Ranged>();
+    ecs.register::<InBackpack>();
+    ecs.register::<WantsToPickupItem>();
+    ecs.register::<WantsToUseItem>();
+    ecs.register::<WantsToDropItem>();
+    ecs.register::<WantsToThrowItem>();
+    ecs.register::<Disable>();
+    ecs.register::<Equippable>();
+    ecs.register::<Equipped>();
+    ecs.register::<Gun>();
+    ecs.register::<Ammo>();
+    ecs.register::<WantsToShoot>();
+    ecs.register::<WantsToReload>();
+    ecs.register::<ParticleLifetime>();
+    ecs.register::<SimpleMarker<SerializeMe>>();
+    ecs.register::<SerializationHelper>();
+}
+
+// A bare world with just what the spawner needs, for tests that spawn things.
+#[cfg(test)]
+fn test_world(seed: u64) -> World {
+    let mut ecs = World::new();
+    register_components(&mut ecs);
+    ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+    ecs.insert(rltk::RandomNumberGenerator::seeded(seed));
+    ecs.insert(identification_system::ItemKnowledge::default());
+    ecs
+}
+
 fn main() -> rltk::BError {
     use rltk::RltkBuilder;
+
+    let options = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, cli::USAGE);
+            ::std::process::exit(2);
+        }
+    };
+    if let Some(count) = options.generate {
+        cli::generate_maps(&options, count)?;
+        return Ok(());
+    }
+
     let mut context = RltkBuilder::simple80x50()
         .with_title("Main/Frame")
         .build()?;
     context.with_post_scanlines(true);
-    let mut gs = State { ecs: World::new() };
-    gs.ecs.register::<Position>();
-    gs.ecs.register::<Renderable>();
-    gs.ecs.register::<Player>();
-    gs.ecs.register::<Viewshed>();
-    gs.ecs.register::<Monster>();
-    gs.ecs.register::<Name>();
-    gs.ecs.register::<BlocksTile>();
-    gs.ecs.register::<CombatStats>();
-    gs.ecs.register::<WantsToMelee>();
-    gs.ecs.register::<SufferDamage>();
-    gs.ecs.register::<Item>();
-    gs.ecs.register::<ProvidesHealing>();
-    gs.ecs.register::<InflictsDamage>();
-    gs.ecs.register::<AreaOfEffect>();
-    gs.ecs.register::<Consumeable>();
-    gs.ecs.register::<Ranged>();
-    gs.ecs.register::<InBackpack>();
-    gs.ecs.register::<WantsToPickupItem>();
-    gs.ecs.register::<WantsToUseItem>();
-    gs.ecs.register::<WantsToDropItem>();
-    gs.ecs.register::<Disable>();
-    gs.ecs.register::<SimpleMarker<SerializeMe>>();
-    gs.ecs.register::<SerializationHelper>();
+    let mut gs = State {
+        ecs: World::new(),
+        // debug flag: replay how the map was built before the game starts
+        mapgen_visualizer: options.show_mapgen,
+        mapgen_history: Vec::new(),
+        mapgen_index: 0,
+        mapgen_timer: 0.0,
+        cursor: None,
+    };
+    register_components(&mut gs.ecs);
 
     gs.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
     // https://specs.amethyst.rs/docs/tutorials/13_saveload.html
 
-    let map: Map = Map::new_map_rooms_and_corridors();
-    let (player_x, player_y) = map.rooms[0].center();
-
-
-    let player_entity = spawner::player(&mut gs.ecs, player_x, player_y);
-
-    gs.ecs.insert(rltk::RandomNumberGenerator::new());
-    for room in map.rooms.iter().skip(1) {
-        spawner::spawn_room(&mut gs.ecs, room);
-    }
-
-    gs.ecs.insert(map);
-
-    gs.ecs.insert(Point::new(player_x, player_y));
+    let seed = cli::seed(&options);
+    gs.ecs.insert(rltk::RandomNumberGenerator::seeded(seed));
+    gs.ecs.insert(Map::default());
+    gs.ecs.insert(Point::new(0, 0));
+    gs.ecs.insert(particle_system::ParticleBuilder::default());
+    gs.ecs
+        .insert(identification_system::ItemKnowledge::default());
+    gs.ecs.insert(crafting::load_recipes());
+    gs.ecs.insert(auto_move::AutoMove::default());
+    let player_entity = spawner::player(&mut gs.ecs, 0, 0);
     gs.ecs.insert(player_entity);
-    gs.ecs.insert(RunState::MainMenu{
-        menu_selection: gui::MainMenuSelection::NewGame
+    gs.generate_world_map(1);
+
+    gs.ecs.insert(RunState::MainMenu {
+        menu_selection: gui::MainMenuSelection::NewGame,
     }); // add START MENU
     gs.ecs.insert(gamelog::GameLog {
-        entries: vec!["Welcome to MainFrame".to_string()],
+        entries: vec![
+            "Welcome to MainFrame".to_string(),
+            format!("Run seed: {}", seed),
+        ],
     });
 
     rltk::main_loop(context, gs)
@@ -0,0 +1,163 @@
+use super::{
+    gamelog::GameLog, identification_system::ItemKnowledge, Disable, Equippable, InBackpack,
+    Installed, Malware, Map, Name, ObfuscatedName, Position, ProvidesAntivirus, TileType, Viewshed,
+    WantsToUseItem,
+};
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+// How long a backfiring chip locks up its user.
+const LOCKUP_TURNS: i32 = 3;
+
+// Infected chips and implants turn on whoever uses them: the effect lands on the user instead of
+// the target, and sometimes the glitch throws them across the level or locks them up as well.
+// Antivirus cleans everything the user carries. Infected equipment is handled by ItemEquipOnUse.
+pub struct MalwareSystem {}
+
+impl<'a> System<'a> for MalwareSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        ReadExpect<'a, Map>,
+        WriteExpect<'a, Point>,
+        ReadExpect<'a, ItemKnowledge>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToUseItem>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, ObfuscatedName>,
+        WriteStorage<'a, Malware>,
+        ReadStorage<'a, ProvidesAntivirus>,
+        ReadStorage<'a, Equippable>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, Installed>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Viewshed>,
+        WriteStorage<'a, Disable>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            mut rng,
+            map,
+            mut player_pos,
+            knowledge,
+            entities,
+            mut wants_to_use,
+            names,
+            obfuscated,
+            mut malware,
+            provides_antivirus,
+            equippable,
+            backpack,
+            installed,
+            mut positions,
+            mut viewsheds,
+            mut disabled,
+        ) = data;
+
+        for (entity, useitem) in (&entities, &mut wants_to_use).join() {
+            let is_player = entity == *player_entity;
+
+            if provides_antivirus.get(useitem.item).is_some() {
+                let mut infected: Vec<Entity> = Vec::new();
+                for (item, _malware) in (&entities, &malware).join() {
+                    let carried = backpack.get(item).is_some_and(|p| p.owner == entity);
+                    let implanted = installed.get(item).is_some_and(|i| i.owner == entity);
+                    if item != useitem.item && (carried || implanted) {
+                        infected.push(item);
+                    }
+                }
+                for item in infected.iter() {
+                    malware.remove(*item);
+                }
+                if is_player {
+                    if infected.is_empty() {
+                        gamelog
+                            .entries
+                            .push("The scan comes back clean.".to_string());
+                    } else {
+                        gamelog.entries.push(format!(
+                            "You purge malware from {} item(s).",
+                            infected.len()
+                        ));
+                    }
+                }
+                continue;
+            }
+
+            if equippable.get(useitem.item).is_some() {
+                continue;
+            }
+            let infection = match malware.get_mut(useitem.item) {
+                None => continue,
+                Some(infection) => infection,
+            };
+            infection.revealed = true;
+            let pos = match positions.get(entity) {
+                None => continue,
+                Some(pos) => Point::new(pos.x, pos.y),
+            };
+            if is_player {
+                let name = knowledge.display_name(
+                    names.get(useitem.item).unwrap(),
+                    obfuscated.get(useitem.item),
+                );
+                gamelog
+                    .entries
+                    .push(format!("The {} is infected and turns on you!", name));
+            }
+
+            let mut pos = pos;
+            match rng.roll_dice(1, 3) {
+                1 => {
+                    let mut destination = None;
+                    while destination.is_none() {
+                        let x = rng.range(1, map.width - 1);
+                        let y = rng.range(1, map.height - 1);
+                        let idx = map.xy_idx(x, y);
+                        if map.tiles[idx] == TileType::Floor && !map.blocked[idx] {
+                            destination = Some(Point::new(x, y));
+                        }
+                    }
+                    pos = destination.unwrap();
+                    if let Some(user_pos) = positions.get_mut(entity) {
+                        user_pos.x = pos.x;
+                        user_pos.y = pos.y;
+                    }
+                    if let Some(viewshed) = viewsheds.get_mut(entity) {
+                        viewshed.dirty = true;
+                    }
+                    if is_player {
+                        *player_pos = pos;
+                        gamelog
+                            .entries
+                            .push("The world glitches and you are somewhere else.".to_string());
+                    }
+                }
+                2 => {
+                    disabled
+                        .insert(
+                            entity,
+                            Disable {
+                                turns: LOCKUP_TURNS,
+                            },
+                        )
+                        .expect("Unable to lock up");
+                    if is_player {
+                        gamelog.entries.push("Your systems lock up!".to_string());
+                    }
+                }
+                _ => {}
+            }
+
+            // whatever it was aimed at, it goes off in the user's face
+            if useitem.target.is_some() {
+                useitem.target = Some(pos);
+            }
+        }
+    }
+}
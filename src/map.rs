@@ -1,5 +1,5 @@
 use super::Rect;
-use rltk::{Algorithm2D, BaseMap, Point, RandomNumberGenerator, Rltk, RGB};
+use rltk::{Algorithm2D, BaseMap, Point, Rltk, RGB};
 use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 use std::cmp::{max, min};
@@ -7,11 +7,13 @@ use std::cmp::{max, min};
 pub const MAPWIDTH: usize = 80;
 pub const MAPHEIGHT: usize = 43;
 pub const MAPCOUNT: usize = MAPWIDTH * MAPHEIGHT;
+const MIN_LIGHT: f32 = 0.15;
 
-#[derive(PartialEq, Copy, Serialize, Deserialize, Clone)]
+#[derive(PartialEq, Eq, Hash, Copy, Serialize, Deserialize, Clone)]
 pub enum TileType {
     Wall,
     Floor,
+    DownStairs,
 }
 
 #[derive(Default, Serialize, Deserialize, Clone)]
@@ -23,10 +25,16 @@ pub struct Map {
     pub revealed_tiles: Vec<bool>,
     pub visible_tiles: Vec<bool>,
     pub blocked: Vec<bool>,
+    pub depth: i32,
 
     #[serde(skip_serializing)]
     #[serde(skip_deserializing)]
     pub tile_content: Vec<Vec<Entity>>,
+
+    // recalculated every turn by the lighting system
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing)]
+    pub light: Vec<RGB>,
 }
 
 impl Algorithm2D for Map {
@@ -104,7 +112,7 @@ impl Map {
         }
     }
 
-    fn apply_room_to_map(&mut self, room: &Rect) {
+    pub fn apply_room_to_map(&mut self, room: &Rect) {
         for y in room.y1 + 1..=room.y2 {
             for x in room.x1 + 1..=room.x2 {
                 let idx = self.xy_idx(x, y);
@@ -113,20 +121,20 @@ impl Map {
         }
     }
 
-    fn apply_horizontal_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
+    pub fn apply_horizontal_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
         for x in min(x1, x2)..=max(x1, x2) {
             let idx = self.xy_idx(x, y);
             if idx > 0 && idx < self.width as usize * self.height as usize {
-                self.tiles[idx as usize] = TileType::Floor;
+                self.tiles[idx] = TileType::Floor;
             }
         }
     }
 
-    fn apply_vertical_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
+    pub fn apply_vertical_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
         for y in min(y1, y2)..=max(y1, y2) {
             let idx = self.xy_idx(x, y);
             if idx > 0 && idx < self.width as usize * self.height as usize {
-                self.tiles[idx as usize] = TileType::Floor;
+                self.tiles[idx] = TileType::Floor;
             }
         }
     }
@@ -136,62 +144,56 @@ impl Map {
         }
     }
 
-    pub fn new_map_rooms_and_corridors() -> Map {
-        let mut map = Map {
-            tiles: vec![TileType::Wall; MAPCOUNT],
+    // Returns a map of the given size, filled entirely with walls
+    pub fn new_empty(width: i32, height: i32) -> Map {
+        let count = (width * height) as usize;
+        Map {
+            tiles: vec![TileType::Wall; count],
             rooms: Vec::new(),
-            width: MAPWIDTH as i32,
-            height: MAPHEIGHT as i32,
-            revealed_tiles: vec![false; MAPCOUNT],
-            visible_tiles: vec![false; MAPCOUNT],
-            blocked: vec![false; MAPCOUNT],
-            tile_content: vec![Vec::new(); MAPCOUNT],
-        };
+            width,
+            height,
+            revealed_tiles: vec![false; count],
+            visible_tiles: vec![false; count],
+            blocked: vec![false; count],
+            depth: 0,
+            tile_content: vec![Vec::new(); count],
+            light: vec![RGB::from_f32(0.0, 0.0, 0.0); count],
+        }
+    }
 
-        const MAX_ROOMS: i32 = 30;
-        const MIN_SIZE: i32 = 6;
-        const MAX_SIZE: i32 = 10;
-
-        let mut rng = RandomNumberGenerator::new();
-
-        for _i in 0..MAX_ROOMS {
-            let w = rng.range(MIN_SIZE, MAX_SIZE);
-            let h = rng.range(MIN_SIZE, MAX_SIZE);
-            let x = rng.roll_dice(1, map.width - w - 1) - 1;
-            let y = rng.roll_dice(1, map.height - h - 1) - 1;
-            let new_room = Rect::new(x, y, w, h);
-            let mut ok = true;
-            for other_room in map.rooms.iter() {
-                if new_room.intersect(other_room) {
-                    ok = false
-                }
+    // Where something fired or thrown from `from` towards `to` comes to rest: on the first
+    // blocking entity in the way, or just short of the first wall.
+    pub fn projectile_impact(&self, from: Point, to: Point) -> Point {
+        let mut last = from;
+        for point in rltk::line2d(rltk::LineAlg::Bresenham, from, to)
+            .iter()
+            .skip(1)
+        {
+            if point.x < 0 || point.x >= self.width || point.y < 0 || point.y >= self.height {
+                return last;
             }
-            if ok {
-                map.apply_room_to_map(&new_room);
-
-                if !map.rooms.is_empty() {
-                    let (new_x, new_y) = new_room.center();
-                    let (prev_x, prev_y) = map.rooms[map.rooms.len() - 1].center();
-                    if rng.range(0, 2) == 1 {
-                        map.apply_horizontal_tunnel(prev_x, new_x, prev_y);
-                        map.apply_vertical_tunnel(prev_y, new_y, new_x);
-                    } else {
-                        map.apply_vertical_tunnel(prev_y, new_y, prev_x);
-                        map.apply_horizontal_tunnel(prev_x, new_x, new_y);
-                    }
-                }
-
-                map.rooms.push(new_room);
+            let idx = self.xy_idx(point.x, point.y);
+            if self.tiles[idx] == TileType::Wall {
+                return last;
+            }
+            if self.blocked[idx] {
+                return *point;
             }
+            last = *point;
         }
+        last
+    }
 
-        map
+    // Too dark to make anything out below this much light.
+    pub fn is_lit(&self, idx: usize) -> bool {
+        match self.light.get(idx) {
+            None => false,
+            Some(light) => light.r + light.g + light.b > MIN_LIGHT,
+        }
     }
 }
 
-pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
-    let map = ecs.fetch::<Map>();
-
+pub fn draw_map(map: &Map, ctx: &mut Rltk) {
     let mut y = 0;
     let mut x = 0;
 
@@ -208,16 +210,22 @@ pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
                     glyph = rltk::to_cp437('#');
                     fg = RGB::from_f32(0.4, 1.0, 0.2)
                 }
+                TileType::DownStairs => {
+                    glyph = rltk::to_cp437('>');
+                    fg = RGB::named(rltk::MAGENTA);
+                }
             }
             if !map.visible_tiles[idx] {
                 fg = fg.to_greyscale();
+            } else if let Some(light) = map.light.get(idx) {
+                fg = fg * *light;
             }
             ctx.set(x, y, fg, RGB::from_f32(0., 0., 0.), glyph);
         }
 
         // Move the coordinates
         x += 1;
-        if x > MAPWIDTH as i32 - 1 {
+        if x > map.width - 1 {
             x = 0;
             y += 1;
         }
@@ -0,0 +1,59 @@
+use super::{Map, TileType};
+use rltk::{RandomNumberGenerator, RGB};
+use std::collections::HashMap;
+
+// Splits the walkable area into Voronoi cells, so maps without rooms still get room sized areas
+// to hand to spawner::spawn_region. Keyed by seed, each cell lists its tile indices.
+pub fn generate_voronoi_spawn_regions(
+    map: &Map,
+    rng: &mut RandomNumberGenerator,
+    seed_count: usize,
+) -> HashMap<usize, Vec<usize>> {
+    let floors: Vec<usize> = map
+        .tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, tile)| **tile == TileType::Floor)
+        .map(|(idx, _)| idx)
+        .collect();
+
+    let mut seeds: Vec<usize> = Vec::new();
+    while seeds.len() < usize::min(seed_count, floors.len()) {
+        let seed = floors[rng.range(0, floors.len())];
+        if !seeds.contains(&seed) {
+            seeds.push(seed);
+        }
+    }
+
+    let width = map.width as usize;
+    let mut regions: HashMap<usize, Vec<usize>> = HashMap::new();
+    for idx in floors {
+        let (x, y) = ((idx % width) as i32, (idx / width) as i32);
+        let closest = seeds
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, seed)| {
+                let (sx, sy) = ((**seed % width) as i32, (**seed / width) as i32);
+                i32::abs(sx - x) + i32::abs(sy - y)
+            })
+            .map(|(region, _)| region)
+            .unwrap();
+        regions.entry(closest).or_default().push(idx);
+    }
+    regions
+}
+
+// A copy of the map with every tile revealed, for the map generation visualizer.
+pub fn snapshot(map: &Map) -> Map {
+    let mut snapshot = map.clone();
+    for revealed in snapshot.revealed_tiles.iter_mut() {
+        *revealed = true;
+    }
+    for visible in snapshot.visible_tiles.iter_mut() {
+        *visible = true;
+    }
+    for light in snapshot.light.iter_mut() {
+        *light = RGB::named(rltk::WHITE);
+    }
+    snapshot
+}
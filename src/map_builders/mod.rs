@@ -0,0 +1,51 @@
+use super::{spawner, Map, Position, Rect, TileType, MAPHEIGHT, MAPWIDTH};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+mod common;
+pub use common::*;
+mod simple_map;
+use simple_map::SimpleMapBuilder;
+mod waveform_collapse;
+use waveform_collapse::{WaveformCollapseBuilder, WaveformSample};
+
+pub trait MapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator);
+    fn spawn_entities(&mut self, ecs: &mut World);
+    fn get_map(&self) -> Map;
+    fn get_starting_position(&self) -> Position;
+    fn get_snapshot_history(&self) -> Vec<Map>;
+    fn take_snapshot(&mut self);
+}
+
+// Picks one of the available generators, so each floor of the facility can look different.
+pub fn random_builder(new_depth: i32, rng: &mut RandomNumberGenerator) -> Box<dyn MapBuilder> {
+    let roll = rng.roll_dice(1, 4);
+    match roll {
+        1 => builder_by_name("wfc-offices", new_depth).unwrap(),
+        2 => builder_by_name("wfc-server-farm", new_depth).unwrap(),
+        3 => builder_by_name("wfc-rooms", new_depth).unwrap(),
+        _ => builder_by_name("simple", new_depth).unwrap(),
+    }
+}
+
+pub const BUILDER_NAMES: [&str; 4] = ["simple", "wfc-offices", "wfc-server-farm", "wfc-rooms"];
+
+pub fn builder_by_name(name: &str, new_depth: i32) -> Option<Box<dyn MapBuilder>> {
+    match name {
+        "simple" => Some(Box::new(SimpleMapBuilder::new(new_depth))),
+        "wfc-offices" => Some(Box::new(WaveformCollapseBuilder::new(
+            new_depth,
+            WaveformSample::Offices,
+        ))),
+        "wfc-server-farm" => Some(Box::new(WaveformCollapseBuilder::new(
+            new_depth,
+            WaveformSample::ServerFarm,
+        ))),
+        "wfc-rooms" => Some(Box::new(WaveformCollapseBuilder::new(
+            new_depth,
+            WaveformSample::RoomsAndCorridors,
+        ))),
+        _ => None,
+    }
+}
@@ -0,0 +1,123 @@
+use super::{Map, TileType};
+use std::collections::HashSet;
+
+pub const NORTH: usize = 0;
+pub const SOUTH: usize = 1;
+pub const WEST: usize = 2;
+pub const EAST: usize = 3;
+
+pub fn opposite(direction: usize) -> usize {
+    match direction {
+        NORTH => SOUTH,
+        SOUTH => NORTH,
+        WEST => EAST,
+        _ => WEST,
+    }
+}
+
+// A chunk_size x chunk_size block of tiles, plus what it may sit next to.
+pub struct MapChunk {
+    pub pattern: Vec<TileType>,
+    pub exits: [Vec<bool>; 4],
+    pub has_exits: bool,
+    pub compatible_with: [Vec<bool>; 4],
+}
+
+// Cut the sample into chunks. Each chunk is also mirrored horizontally, vertically and both ways,
+// so a small sample still yields enough variety.
+pub fn build_patterns(map: &Map, chunk_size: i32) -> Vec<Vec<TileType>> {
+    let chunks_x = map.width / chunk_size;
+    let chunks_y = map.height / chunk_size;
+    let mut patterns: HashSet<Vec<TileType>> = HashSet::new();
+
+    for cy in 0..chunks_y {
+        for cx in 0..chunks_x {
+            let start_x = cx * chunk_size;
+            let start_y = cy * chunk_size;
+
+            for (flip_x, flip_y) in
+                [(false, false), (true, false), (false, true), (true, true)].iter()
+            {
+                let mut pattern: Vec<TileType> = Vec::new();
+                for dy in 0..chunk_size {
+                    for dx in 0..chunk_size {
+                        let x = if *flip_x {
+                            start_x + chunk_size - 1 - dx
+                        } else {
+                            start_x + dx
+                        };
+                        let y = if *flip_y {
+                            start_y + chunk_size - 1 - dy
+                        } else {
+                            start_y + dy
+                        };
+                        pattern.push(map.tiles[map.xy_idx(x, y)]);
+                    }
+                }
+                patterns.insert(pattern);
+            }
+        }
+    }
+
+    // sort so the same sample and seed always give the same map
+    let mut patterns: Vec<Vec<TileType>> = patterns.into_iter().collect();
+    patterns.sort_by_key(|p| {
+        p.iter()
+            .map(|t| if *t == TileType::Floor { '.' } else { '#' })
+            .collect::<String>()
+    });
+    patterns
+}
+
+// Two chunks may touch if neither has an opening on the shared edge, or if at least one opening
+// lines up so you can walk from one into the other.
+pub fn patterns_to_constraints(patterns: Vec<Vec<TileType>>, chunk_size: i32) -> Vec<MapChunk> {
+    let size = chunk_size as usize;
+    let mut constraints: Vec<MapChunk> = Vec::new();
+
+    for pattern in patterns {
+        let mut exits = [
+            vec![false; size],
+            vec![false; size],
+            vec![false; size],
+            vec![false; size],
+        ];
+        for i in 0..size {
+            exits[NORTH][i] = pattern[i] == TileType::Floor;
+            exits[SOUTH][i] = pattern[(size - 1) * size + i] == TileType::Floor;
+            exits[WEST][i] = pattern[i * size] == TileType::Floor;
+            exits[EAST][i] = pattern[i * size + size - 1] == TileType::Floor;
+        }
+        let has_exits = exits.iter().any(|side| side.iter().any(|e| *e));
+
+        constraints.push(MapChunk {
+            pattern,
+            exits,
+            has_exits,
+            compatible_with: [Vec::new(), Vec::new(), Vec::new(), Vec::new()],
+        });
+    }
+
+    let count = constraints.len();
+    for i in 0..count {
+        for direction in [NORTH, SOUTH, WEST, EAST].iter() {
+            let direction = *direction;
+            let mut compatible = vec![false; count];
+            for (j, other) in constraints.iter().enumerate() {
+                let ours = &constraints[i].exits[direction];
+                let theirs = &other.exits[opposite(direction)];
+
+                let our_open = ours.iter().any(|e| *e);
+                let their_open = theirs.iter().any(|e| *e);
+                compatible[j] = if !our_open && !their_open {
+                    true
+                } else {
+                    ours.iter().zip(theirs.iter()).any(|(a, b)| *a && *b)
+                };
+            }
+            constraints[i].compatible_with[direction] = compatible;
+        }
+    }
+
+    constraints
+}
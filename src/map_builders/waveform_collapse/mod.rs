@@ -0,0 +1,250 @@
+use super::{
+    generate_voronoi_spawn_regions, snapshot, spawner, Map, MapBuilder, Position, Rect,
+    SimpleMapBuilder, TileType, MAPHEIGHT, MAPWIDTH,
+};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+use std::collections::HashMap;
+
+mod constraints;
+use constraints::{build_patterns, patterns_to_constraints};
+mod solver;
+use solver::{Solver, SolverStep};
+mod samples;
+use samples::{SampleMap, OFFICES, SERVER_FARM};
+
+const MAX_ATTEMPTS: i32 = 20;
+const VORONOI_SEEDS: usize = 32;
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum WaveformSample {
+    Offices,
+    ServerFarm,
+    RoomsAndCorridors,
+}
+
+// Learns which chunks of tiles may sit next to each other from a sample, then grows a full size
+// map that follows the same rules.
+pub struct WaveformCollapseBuilder {
+    map: Map,
+    starting_position: Position,
+    sample: WaveformSample,
+    spawn_regions: HashMap<usize, Vec<usize>>,
+    depth: i32,
+    history: Vec<Map>,
+}
+
+impl WaveformCollapseBuilder {
+    pub fn new(new_depth: i32, sample: WaveformSample) -> WaveformCollapseBuilder {
+        WaveformCollapseBuilder {
+            map: Map::default(),
+            depth: new_depth,
+            starting_position: Position { x: 0, y: 0 },
+            sample,
+            spawn_regions: HashMap::new(),
+            history: Vec::new(),
+        }
+    }
+
+    fn sample_map(&self, rng: &mut RandomNumberGenerator) -> (Map, i32) {
+        match self.sample {
+            WaveformSample::Offices => (load_sample(&OFFICES), 4),
+            WaveformSample::ServerFarm => (load_sample(&SERVER_FARM), 4),
+            WaveformSample::RoomsAndCorridors => {
+                let mut builder = SimpleMapBuilder::new(self.depth);
+                builder.build_map(rng);
+                let mut sample = builder.get_map();
+                // stairs are placed after the collapse, don't let them leak into the patterns
+                for tile in sample.tiles.iter_mut() {
+                    if *tile == TileType::DownStairs {
+                        *tile = TileType::Floor;
+                    }
+                }
+                (sample, 8)
+            }
+        }
+    }
+
+    // Runs the solver until it produces a map with enough reachable floor. Returns false if it
+    // never did.
+    fn collapse(&mut self, rng: &mut RandomNumberGenerator) -> bool {
+        let (sample, chunk_size) = self.sample_map(rng);
+        self.history.push(snapshot(&sample));
+        let patterns = build_patterns(&sample, chunk_size);
+
+        for _attempt in 0..MAX_ATTEMPTS {
+            self.map = Map::new_empty(MAPWIDTH as i32, MAPHEIGHT as i32);
+            self.map.depth = self.depth;
+            let mut solver = Solver::new(
+                patterns_to_constraints(patterns.clone(), chunk_size),
+                chunk_size,
+                &self.map,
+            );
+
+            let mut placed = 0;
+            let mut step = SolverStep::Placed;
+            while step == SolverStep::Placed {
+                step = solver.step(&mut self.map, rng);
+                placed += 1;
+                if placed % solver.chunks_per_row() == 0 {
+                    self.take_snapshot();
+                }
+            }
+            if step == SolverStep::Contradiction {
+                continue;
+            }
+
+            wall_off_edges(&mut self.map);
+            self.map.populate_blocked();
+
+            let start = match nearest_floor(&self.map, self.map.width / 2, self.map.height / 2) {
+                None => continue,
+                Some(start) => start,
+            };
+            let (reachable, farthest) = cull_unreachable(&mut self.map, start);
+            self.take_snapshot();
+            if reachable < (self.map.width * self.map.height) as usize / 4 {
+                continue;
+            }
+
+            // the way down is as far from the start as the map allows
+            self.map.tiles[farthest] = TileType::DownStairs;
+            self.take_snapshot();
+
+            self.starting_position = Position {
+                x: start as i32 % self.map.width,
+                y: start as i32 / self.map.width,
+            };
+            return true;
+        }
+        false
+    }
+}
+
+impl MapBuilder for WaveformCollapseBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        if !self.collapse(rng) {
+            // the sample is too restrictive for this seed, fall back to a map we know works
+            let mut fallback = SimpleMapBuilder::new(self.depth);
+            fallback.build_map(rng);
+            self.map = fallback.get_map();
+            self.starting_position = fallback.get_starting_position();
+            self.history.append(&mut fallback.get_snapshot_history());
+        }
+        self.spawn_regions = generate_voronoi_spawn_regions(&self.map, rng, VORONOI_SEEDS);
+        if self.map.rooms.is_empty() {
+            self.map.rooms = region_bounds(&self.map, &self.spawn_regions);
+        }
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        let start = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+        let mut regions: Vec<&usize> = self.spawn_regions.keys().collect();
+        regions.sort();
+        for region in regions {
+            let area = &self.spawn_regions[region];
+            // leave the player a moment to get their bearings
+            if !area.contains(&start) {
+                spawner::spawn_region(ecs, &self.map, area);
+            }
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_snapshot_history(&self) -> Vec<Map> {
+        self.history.clone()
+    }
+
+    fn take_snapshot(&mut self) {
+        self.history.push(snapshot(&self.map));
+    }
+}
+
+// A collapsed map has no rooms as such, so the spawn regions stand in for them: each one becomes
+// the rectangle that bounds its tiles.
+fn region_bounds(map: &Map, regions: &HashMap<usize, Vec<usize>>) -> Vec<Rect> {
+    let mut keys: Vec<&usize> = regions.keys().collect();
+    keys.sort();
+    keys.iter()
+        .map(|key| {
+            let area = &regions[key];
+            let xs = area.iter().map(|idx| *idx as i32 % map.width);
+            let ys = area.iter().map(|idx| *idx as i32 / map.width);
+            let (x1, x2) = (xs.clone().min().unwrap(), xs.max().unwrap());
+            let (y1, y2) = (ys.clone().min().unwrap(), ys.max().unwrap());
+            Rect::new(x1, y1, x2 - x1, y2 - y1)
+        })
+        .collect()
+}
+
+fn load_sample(sample: &SampleMap) -> Map {
+    let mut map = Map::new_empty(sample.width, sample.height);
+    for (idx, glyph) in sample.template.chars().enumerate() {
+        if idx < map.tiles.len() {
+            map.tiles[idx] = match glyph {
+                '.' => TileType::Floor,
+                _ => TileType::Wall,
+            };
+        }
+    }
+    map
+}
+
+fn wall_off_edges(map: &mut Map) {
+    for x in 0..map.width {
+        let top = map.xy_idx(x, 0);
+        let bottom = map.xy_idx(x, map.height - 1);
+        map.tiles[top] = TileType::Wall;
+        map.tiles[bottom] = TileType::Wall;
+    }
+    for y in 0..map.height {
+        let left = map.xy_idx(0, y);
+        let right = map.xy_idx(map.width - 1, y);
+        map.tiles[left] = TileType::Wall;
+        map.tiles[right] = TileType::Wall;
+    }
+}
+
+fn nearest_floor(map: &Map, x: i32, y: i32) -> Option<usize> {
+    map.tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, tile)| **tile == TileType::Floor)
+        .min_by_key(|(idx, _)| {
+            let (tx, ty) = (*idx as i32 % map.width, *idx as i32 / map.width);
+            i32::abs(tx - x) + i32::abs(ty - y)
+        })
+        .map(|(idx, _)| idx)
+}
+
+// Walls in any floor the player can't walk to. Returns the number of floor tiles left, and the
+// one furthest from the start.
+fn cull_unreachable(map: &mut Map, start: usize) -> (usize, usize) {
+    let dijkstra = rltk::DijkstraMap::new(map.width, map.height, &[start], &*map, 1000.0);
+    let mut reachable = 0;
+    let mut farthest = (start, 0.0);
+    for (idx, tile) in map.tiles.iter_mut().enumerate() {
+        if *tile == TileType::Floor {
+            let distance = dijkstra.map[idx];
+            if distance == f32::MAX {
+                *tile = TileType::Wall;
+            } else {
+                reachable += 1;
+                if distance > farthest.1 {
+                    farthest = (idx, distance);
+                }
+            }
+        }
+    }
+    map.populate_blocked();
+    (reachable, farthest.0)
+}
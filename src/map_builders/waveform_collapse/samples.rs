@@ -0,0 +1,55 @@
+// Hand-drawn samples the collapse learns its tile adjacency from. `#` is a wall, `.` is floor.
+
+pub struct SampleMap {
+    pub template: &'static str,
+    pub width: i32,
+    pub height: i32,
+}
+
+// Cubicle rows and meeting rooms, split by wide corridors.
+pub const OFFICES: SampleMap = SampleMap {
+    template: "\
+    ################################\
+    #......#.......#......#........#\
+    #.##.#.#.##.##.#.####.#.#.####.#\
+    #......#.......#......#........#\
+    #.#..#...#..#......#......#..#.#\
+    ###.####.###.####.####.##.#.####\
+    #..............................#\
+    #.####.####.#.####.####.####.#.#\
+    #.#..#.#..#...#..#.#..#.#..#...#\
+    #.#..#.#..#.#.#..#.#..#.#..#.#.#\
+    #.##.#.##.#.#.##.#.##.#.##.#.#.#\
+    #..............................#\
+    ####.#####.####.#####.###.######\
+    #.......#.........#.......#....#\
+    #.#.##..#..####...#..###..#.##.#\
+    ################################\
+    ",
+    width: 32,
+    height: 16,
+};
+
+// Long aisles between racks, with a few maintenance rooms.
+pub const SERVER_FARM: SampleMap = SampleMap {
+    template: "\
+    ################################\
+    #..............................#\
+    #.##.##.##.##.###.##.##.##.##..#\
+    #..............#...............#\
+    #.##.##.##.##..#..##.##.##.##..#\
+    #..............#...............#\
+    #######.########.#######.#######\
+    #......................#.......#\
+    #.###.###.###.###.###..#.#####.#\
+    #.#.#.#.#.#.#.#.#.#.#..#.#...#.#\
+    #.###.###.###.###.###..#.#...#.#\
+    #......................#.##.##.#\
+    #######.##########.#####.......#\
+    #.....#......#.....#.....#.....#\
+    #..............................#\
+    ################################\
+    ",
+    width: 32,
+    height: 16,
+};
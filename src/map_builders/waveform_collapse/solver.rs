@@ -0,0 +1,150 @@
+use super::constraints::{opposite, MapChunk, EAST, NORTH, SOUTH, WEST};
+use super::Map;
+use rltk::RandomNumberGenerator;
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum SolverStep {
+    Placed,
+    Done,
+    // a chunk has no pattern left that fits its neighbours
+    Contradiction,
+}
+
+pub struct Solver {
+    constraints: Vec<MapChunk>,
+    chunk_size: i32,
+    chunks: Vec<Option<usize>>,
+    chunks_x: i32,
+    chunks_y: i32,
+}
+
+impl Solver {
+    pub fn new(constraints: Vec<MapChunk>, chunk_size: i32, map: &Map) -> Solver {
+        let chunks_x = map.width / chunk_size;
+        let chunks_y = map.height / chunk_size;
+        Solver {
+            constraints,
+            chunk_size,
+            chunks: vec![None; (chunks_x * chunks_y) as usize],
+            chunks_x,
+            chunks_y,
+        }
+    }
+
+    fn chunk_idx(&self, x: i32, y: i32) -> usize {
+        ((y * self.chunks_x) + x) as usize
+    }
+
+    fn neighbours(&self, x: i32, y: i32) -> Vec<(usize, usize)> {
+        let mut neighbours = Vec::new();
+        if y > 0 {
+            neighbours.push((NORTH, self.chunk_idx(x, y - 1)));
+        }
+        if y < self.chunks_y - 1 {
+            neighbours.push((SOUTH, self.chunk_idx(x, y + 1)));
+        }
+        if x > 0 {
+            neighbours.push((WEST, self.chunk_idx(x - 1, y)));
+        }
+        if x < self.chunks_x - 1 {
+            neighbours.push((EAST, self.chunk_idx(x + 1, y)));
+        }
+        neighbours
+    }
+
+    // Every pattern that agrees with all of the chunk's already collapsed neighbours.
+    fn candidates(&self, x: i32, y: i32) -> Vec<usize> {
+        let mut candidates: Vec<usize> = (0..self.constraints.len()).collect();
+        for (direction, neighbour) in self.neighbours(x, y) {
+            if let Some(placed) = self.chunks[neighbour] {
+                let allowed = &self.constraints[placed].compatible_with[opposite(direction)];
+                candidates.retain(|c| allowed[*c]);
+            }
+        }
+        candidates
+    }
+
+    fn has_collapsed_neighbour(&self, x: i32, y: i32) -> bool {
+        self.neighbours(x, y)
+            .iter()
+            .any(|(_, neighbour)| self.chunks[*neighbour].is_some())
+    }
+
+    // Collapses a single chunk: the one with the fewest options left, or an open chunk in the
+    // middle of the map if nothing has been placed yet. The chunk is drawn onto the map straight
+    // away so the builder can snapshot progress.
+    pub fn step(&mut self, map: &mut Map, rng: &mut RandomNumberGenerator) -> SolverStep {
+        if self.chunks.iter().all(|c| c.is_none()) {
+            let open: Vec<usize> = (0..self.constraints.len())
+                .filter(|c| self.constraints[*c].has_exits)
+                .collect();
+            return match rng.random_slice_entry(&open) {
+                None => SolverStep::Contradiction,
+                Some(pattern) => {
+                    self.place(map, self.chunks_x / 2, self.chunks_y / 2, *pattern);
+                    SolverStep::Placed
+                }
+            };
+        }
+
+        let mut lowest: Option<(i32, i32, Vec<usize>)> = None;
+        let mut ties = 1;
+        for y in 0..self.chunks_y {
+            for x in 0..self.chunks_x {
+                if self.chunks[self.chunk_idx(x, y)].is_some()
+                    || !self.has_collapsed_neighbour(x, y)
+                {
+                    continue;
+                }
+                let candidates = self.candidates(x, y);
+                let replace = match &lowest {
+                    None => {
+                        ties = 1;
+                        true
+                    }
+                    Some((_, _, best)) if candidates.len() < best.len() => {
+                        ties = 1;
+                        true
+                    }
+                    Some((_, _, best)) if candidates.len() == best.len() => {
+                        // reservoir sample among equally constrained chunks
+                        ties += 1;
+                        rng.roll_dice(1, ties) == 1
+                    }
+                    _ => false,
+                };
+                if replace {
+                    lowest = Some((x, y, candidates));
+                }
+            }
+        }
+
+        match lowest {
+            None => SolverStep::Done,
+            Some((x, y, candidates)) => match rng.random_slice_entry(&candidates) {
+                None => SolverStep::Contradiction,
+                Some(pattern) => {
+                    self.place(map, x, y, *pattern);
+                    SolverStep::Placed
+                }
+            },
+        }
+    }
+
+    pub fn chunks_per_row(&self) -> i32 {
+        self.chunks_x
+    }
+
+    fn place(&mut self, map: &mut Map, cx: i32, cy: i32, pattern: usize) {
+        let idx = self.chunk_idx(cx, cy);
+        self.chunks[idx] = Some(pattern);
+
+        let pattern = &self.constraints[pattern].pattern;
+        for dy in 0..self.chunk_size {
+            for dx in 0..self.chunk_size {
+                let idx = map.xy_idx(cx * self.chunk_size + dx, cy * self.chunk_size + dy);
+                map.tiles[idx] = pattern[(dy * self.chunk_size + dx) as usize];
+            }
+        }
+    }
+}
@@ -1,6 +1,16 @@
-use super::{gamelog::GameLog, CombatStats, Name, SufferDamage, WantsToMelee};
+use super::{
+    ability_system::OVERCLOCK_BONUS,
+    encumbrance_system::effective_evasion,
+    gamelog::GameLog,
+    particle_system::{ParticleBuilder, HIT_MS},
+    Cloaked, CombatStats, Encumbered, Name, Overclocked, Position, SufferDamage, WantsToMelee,
+};
+use rltk::{RandomNumberGenerator, RGB};
 use specs::prelude::*;
 
+// An attack hits when d20 + accuracy reaches this plus the target's evasion.
+const BASE_TARGET: i32 = 10;
+
 pub struct MeleeCombatSystem {}
 
 impl<'a> System<'a> for MeleeCombatSystem {
@@ -8,36 +18,103 @@ impl<'a> System<'a> for MeleeCombatSystem {
     type SystemData = (
         Entities<'a>,
         WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
         WriteStorage<'a, WantsToMelee>,
         ReadStorage<'a, Name>,
         ReadStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, Position>,
+        WriteExpect<'a, ParticleBuilder>,
+        WriteStorage<'a, Cloaked>,
+        ReadStorage<'a, Overclocked>,
+        ReadStorage<'a, Encumbered>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut log, mut wants_melee, names, combat_stats, mut inflict_damage) = data;
+        let (
+            entities,
+            mut log,
+            mut rng,
+            mut wants_melee,
+            names,
+            combat_stats,
+            mut inflict_damage,
+            positions,
+            mut particle_builder,
+            mut cloaked,
+            overclocked,
+            encumbered,
+        ) = data;
 
-        for (_entity, wants_melee, name, stats) in
+        for (entity, wants_melee, name, stats) in
             (&entities, &wants_melee, &names, &combat_stats).join()
         {
             if stats.hp > 0 {
                 let target_stats = combat_stats.get(wants_melee.target).unwrap();
                 if target_stats.hp > 0 {
                     let target_name = names.get(wants_melee.target).unwrap();
+                    // attacking gives away a cloak
+                    cloaked.remove(entity);
+                    let bonus = if overclocked.get(entity).is_some() {
+                        OVERCLOCK_BONUS
+                    } else {
+                        0
+                    };
 
-                    let damage = i32::max(0, stats.power - target_stats.defense);
+                    let natural_roll = rng.roll_dice(1, 20);
+                    let critical = natural_roll == 20;
 
-                    if damage == 0 {
+                    if natural_roll == 1 {
                         log.entries.push(format!(
-                            "{} is unable to hurt {}",
+                            "{} fumbles the attack on {}.",
                             &name.name, &target_name.name
-                        ))
-                    } else {
-                        log.entries.push(format!(
-                            "{} hits {}, for {} hp.",
-                            &name.name, &target_name.name, damage
                         ));
-                        SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage);
+                    } else if !critical
+                        && natural_roll + stats.accuracy + bonus
+                            < BASE_TARGET
+                                + effective_evasion(
+                                    target_stats,
+                                    encumbered.get(wants_melee.target),
+                                )
+                    {
+                        log.entries
+                            .push(format!("{} misses {}.", &name.name, &target_name.name));
+                    } else {
+                        let mut roll = rng.roll(stats.damage);
+                        if critical {
+                            // criticals roll the damage dice twice
+                            roll += rng.roll(stats.damage);
+                        }
+                        // a hit always does something, however good the armor
+                        let damage = i32::max(1, roll + stats.power + bonus - target_stats.defense);
+
+                        if critical {
+                            log.entries.push(format!(
+                                "{} lands a critical hit on {}, for {} hp!",
+                                &name.name, &target_name.name, damage
+                            ));
+                        } else {
+                            log.entries.push(format!(
+                                "{} hits {}, for {} hp.",
+                                &name.name, &target_name.name, damage
+                            ));
+                        }
+                        if let Some(pos) = positions.get(wants_melee.target) {
+                            particle_builder.request(
+                                pos.x,
+                                pos.y,
+                                RGB::named(rltk::ORANGE),
+                                RGB::named(rltk::BLACK),
+                                rltk::to_cp437('‼'),
+                                HIT_MS,
+                            );
+                        }
+                        SufferDamage::new_damage(
+                            &mut inflict_damage,
+                            wants_melee.target,
+                            damage,
+                            stats.damage_type,
+                        );
                     }
                 }
             }
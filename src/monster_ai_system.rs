@@ -1,5 +1,5 @@
 extern crate specs;
-use super::{Disable, Map, Monster, Position, RunState, Viewshed, WantsToMelee};
+use super::{Cloaked, Disable, Map, Monster, Position, RunState, Viewshed, WantsToMelee};
 use specs::prelude::*;
 extern crate rltk;
 use rltk::Point;
@@ -19,6 +19,7 @@ impl<'a> System<'a> for MonsterAI {
         WriteStorage<'a, Position>,
         WriteStorage<'a, WantsToMelee>,
         WriteStorage<'a, Disable>,
+        ReadStorage<'a, Cloaked>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -33,13 +34,16 @@ impl<'a> System<'a> for MonsterAI {
             mut position,
             mut wants_to_melee,
             mut disabled,
+            cloaked,
         ) = data;
 
         if *runstate != RunState::MonsterTurn {
             return;
         }
+        // a cloaked player can't be spotted or chased, only hit by whoever is already next to them
+        let player_hidden = cloaked.get(*player_entity).is_some();
 
-        for (entity, mut viewshed, _monster, mut pos) in
+        for (entity, viewshed, _monster, pos) in
             (&entities, &mut viewshed, &monster, &mut position).join()
         {
             let mut can_act = true;
@@ -69,7 +73,7 @@ impl<'a> System<'a> for MonsterAI {
                             },
                         )
                         .expect("Unable to attack!");
-                } else if viewshed.visible_tiles.contains(&*player_pos) {
+                } else if !player_hidden && viewshed.visible_tiles.contains(&*player_pos) {
                     let path = rltk::a_star_search(
                         map.xy_idx(pos.x, pos.y) as i32,
                         map.xy_idx(player_pos.x, player_pos.y) as i32,
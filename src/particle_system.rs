@@ -0,0 +1,141 @@
+use super::{DamageType, ParticleLifetime, Position, Renderable};
+use rltk::{Point, Rltk, RGB};
+use specs::prelude::*;
+
+pub const BEAM_MS: f32 = 150.0;
+pub const BLAST_MS: f32 = 250.0;
+pub const HIT_MS: f32 = 200.0;
+
+// Ages every particle by the frame time and deletes the ones that have run out. Runs every
+// frame, not every turn, so effects fade while the game waits for input.
+pub fn cull_dead_particles(ecs: &mut World, ctx: &Rltk) {
+    let mut dead_particles: Vec<Entity> = Vec::new();
+    {
+        let mut particles = ecs.write_storage::<ParticleLifetime>();
+        let entities = ecs.entities();
+        for (entity, particle) in (&entities, &mut particles).join() {
+            particle.lifetime_ms -= ctx.frame_time_ms;
+            if particle.lifetime_ms < 0.0 {
+                dead_particles.push(entity);
+            }
+        }
+    }
+    for dead in dead_particles.iter() {
+        ecs.delete_entity(*dead).expect("Particle will not die");
+    }
+}
+
+// Effects take the colour of the damage they deal.
+pub fn damage_color(damage_type: DamageType) -> RGB {
+    match damage_type {
+        DamageType::Kinetic => RGB::named(rltk::YELLOW),
+        DamageType::Electric => RGB::named(rltk::CYAN),
+        DamageType::Thermal => RGB::named(rltk::ORANGE),
+        DamageType::Emp => RGB::named(rltk::MAGENTA),
+    }
+}
+
+struct ParticleRequest {
+    x: i32,
+    y: i32,
+    fg: RGB,
+    bg: RGB,
+    glyph: rltk::FontCharType,
+    lifetime: f32,
+}
+
+// Systems queue effects here; ParticleSpawnSystem turns them into entities at the end of the turn.
+#[derive(Default)]
+pub struct ParticleBuilder {
+    requests: Vec<ParticleRequest>,
+}
+
+impl ParticleBuilder {
+    pub fn request(
+        &mut self,
+        x: i32,
+        y: i32,
+        fg: RGB,
+        bg: RGB,
+        glyph: rltk::FontCharType,
+        lifetime: f32,
+    ) {
+        self.requests.push(ParticleRequest {
+            x,
+            y,
+            fg,
+            bg,
+            glyph,
+            lifetime,
+        });
+    }
+
+    // A trail of particles from one point to another, not counting the start.
+    pub fn beam(&mut self, from: Point, to: Point, fg: RGB, glyph: rltk::FontCharType) {
+        for point in rltk::line2d(rltk::LineAlg::Bresenham, from, to)
+            .iter()
+            .skip(1)
+        {
+            self.request(
+                point.x,
+                point.y,
+                fg,
+                RGB::named(rltk::BLACK),
+                glyph,
+                BEAM_MS,
+            );
+        }
+    }
+}
+
+pub struct ParticleSpawnSystem {}
+
+impl<'a> System<'a> for ParticleSpawnSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Renderable>,
+        WriteStorage<'a, ParticleLifetime>,
+        WriteExpect<'a, ParticleBuilder>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut positions, mut renderables, mut particles, mut particle_builder) = data;
+
+        for new_particle in particle_builder.requests.iter() {
+            let p = entities.create();
+            positions
+                .insert(
+                    p,
+                    Position {
+                        x: new_particle.x,
+                        y: new_particle.y,
+                    },
+                )
+                .expect("Unable to insert position");
+            renderables
+                .insert(
+                    p,
+                    Renderable {
+                        fg: new_particle.fg,
+                        bg: new_particle.bg,
+                        glyph: new_particle.glyph,
+                        // drawn over everything else
+                        render_order: -1,
+                    },
+                )
+                .expect("Unable to insert renderable");
+            particles
+                .insert(
+                    p,
+                    ParticleLifetime {
+                        lifetime_ms: new_particle.lifetime,
+                    },
+                )
+                .expect("Unable to insert lifetime");
+        }
+
+        particle_builder.requests.clear();
+    }
+}
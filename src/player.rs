@@ -1,12 +1,17 @@
 use super::{
-    gamelog::GameLog, CombatStats, Item, Map, Player, Position, RunState, State, Viewshed,
-    WantsToMelee, WantsToPickupItem,
+    auto_move,
+    gamelog::GameLog,
+    gui::{ContainerMode, VendorMode},
+    ranged_combat_system::equipped_weapon,
+    Ability, Ammo, AreaOfEffect, CombatStats, Container, Disable, Energy, Equipped, Gun,
+    InBackpack, Item, Locked, Map, Name, Player, Position, Ranged, RunState, State, TileType,
+    Vendor, Viewshed, WantsToMelee, WantsToPickupItem, WantsToReload, WantsToUnlock,
+    WantsToUseItem,
 };
 use rltk::{Point, Rltk, VirtualKeyCode};
 use specs::prelude::*;
-use std::cmp::{max, min};
 
-pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState {
     let mut positions = ecs.write_storage::<Position>();
     let players = ecs.read_storage::<Player>();
     let mut viewsheds = ecs.write_storage::<Viewshed>();
@@ -14,6 +19,10 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
     let combat_stats = ecs.read_storage::<CombatStats>();
     let map = ecs.fetch::<Map>();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
+    let vendors = ecs.read_storage::<Vendor>();
+    let containers = ecs.read_storage::<Container>();
+    let locked = ecs.read_storage::<Locked>();
+    let mut wants_to_unlock = ecs.write_storage::<WantsToUnlock>();
 
     for (entity, _player, pos, viewshed) in
         (&entities, &players, &mut positions, &mut viewsheds).join()
@@ -23,11 +32,36 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
             || pos.y + delta_y < 1
             || pos.y + delta_y > map.height - 1
         {
-            return;
+            return RunState::PlayerTurn;
         }
         let destination_idx = map.xy_idx(pos.x + delta_x, pos.y + delta_y);
 
         for potential_target in map.tile_content[destination_idx].iter() {
+            // bumping into a vendor opens their shop
+            if vendors.get(*potential_target).is_some() {
+                return RunState::ShowVendor {
+                    vendor: *potential_target,
+                    mode: VendorMode::Buy,
+                };
+            }
+            // containers open when bumped, once they're unlocked
+            if containers.get(*potential_target).is_some() {
+                if locked.get(*potential_target).is_some() {
+                    wants_to_unlock
+                        .insert(
+                            entity,
+                            WantsToUnlock {
+                                container: *potential_target,
+                            },
+                        )
+                        .expect("Unable to insert intent");
+                    return RunState::PlayerTurn;
+                }
+                return RunState::ShowContainer {
+                    container: *potential_target,
+                    mode: ContainerMode::Take,
+                };
+            }
             let target = combat_stats.get(*potential_target);
             if let Some(_target) = target {
                 wants_to_melee
@@ -38,12 +72,12 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
                         },
                     )
                     .expect("Add target failed");
-                return;
+                return RunState::PlayerTurn;
             }
         }
         if !map.blocked[destination_idx] {
-            pos.x = min(79, max(0, pos.x + delta_x));
-            pos.y = min(49, max(0, pos.y + delta_y));
+            pos.x = (pos.x + delta_x).clamp(0, 79);
+            pos.y = (pos.y + delta_y).clamp(0, 49);
             viewshed.dirty = true;
 
             let mut player_position = ecs.write_resource::<Point>();
@@ -51,43 +85,80 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
             player_position.y = pos.y;
         }
     }
+    RunState::PlayerTurn
 }
 
 pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
     // Player movement
+    // clicking a tile walks there
+    if ctx.left_click {
+        return auto_move::travel_to_mouse(&mut gs.ecs, ctx);
+    }
     match ctx.key {
         None => return RunState::AwaitingInput,
         Some(key) => match key {
             VirtualKeyCode::Escape => return RunState::SaveGame,
             VirtualKeyCode::D => return RunState::ShowDropItem,
+            VirtualKeyCode::T => return RunState::ShowThrowItem,
             VirtualKeyCode::I => return RunState::ShowInventory,
+            VirtualKeyCode::X => return RunState::Looking,
+            VirtualKeyCode::C => return RunState::ShowCrafting,
+            VirtualKeyCode::O => return auto_move::start_explore(&mut gs.ecs),
+            VirtualKeyCode::S => return auto_move::travel_to_stairs(&mut gs.ecs),
             VirtualKeyCode::G => {
-                get_item(&mut gs.ecs);
+                if get_item(&mut gs.ecs) {
+                    return RunState::ShowPickup;
+                }
+            }
+            VirtualKeyCode::F => return fire_weapon(&mut gs.ecs),
+            VirtualKeyCode::R => return reload_weapon(&mut gs.ecs),
+            VirtualKeyCode::Key1 => return use_ability(&mut gs.ecs, 1),
+            VirtualKeyCode::Key2 => return use_ability(&mut gs.ecs, 2),
+            VirtualKeyCode::Key3 => return use_ability(&mut gs.ecs, 3),
+            VirtualKeyCode::Key4 => return use_ability(&mut gs.ecs, 4),
+            VirtualKeyCode::Key5 => return use_ability(&mut gs.ecs, 5),
+            VirtualKeyCode::Key6 => return use_ability(&mut gs.ecs, 6),
+            VirtualKeyCode::Key7 => return use_ability(&mut gs.ecs, 7),
+            VirtualKeyCode::Key8 => return use_ability(&mut gs.ecs, 8),
+            VirtualKeyCode::Key9 => return use_ability(&mut gs.ecs, 9),
+            VirtualKeyCode::Period => {
+                if try_next_level(&mut gs.ecs) {
+                    return RunState::NextLevel;
+                }
+                return RunState::AwaitingInput;
             }
             VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => {
-                try_move_player(-1, 0, &mut gs.ecs)
+                return try_move_player(-1, 0, &mut gs.ecs)
             }
 
             VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => {
-                try_move_player(1, 0, &mut gs.ecs)
+                return try_move_player(1, 0, &mut gs.ecs)
             }
 
             VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => {
-                try_move_player(0, -1, &mut gs.ecs)
+                return try_move_player(0, -1, &mut gs.ecs)
             }
 
             VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => {
-                try_move_player(0, 1, &mut gs.ecs)
+                return try_move_player(0, 1, &mut gs.ecs)
             }
 
             // Diagonals
-            VirtualKeyCode::Numpad9 | VirtualKeyCode::Y => try_move_player(1, -1, &mut gs.ecs),
+            VirtualKeyCode::Numpad9 | VirtualKeyCode::Y => {
+                return try_move_player(1, -1, &mut gs.ecs)
+            }
 
-            VirtualKeyCode::Numpad7 | VirtualKeyCode::U => try_move_player(-1, -1, &mut gs.ecs),
+            VirtualKeyCode::Numpad7 | VirtualKeyCode::U => {
+                return try_move_player(-1, -1, &mut gs.ecs)
+            }
 
-            VirtualKeyCode::Numpad3 | VirtualKeyCode::N => try_move_player(1, 1, &mut gs.ecs),
+            VirtualKeyCode::Numpad3 | VirtualKeyCode::N => {
+                return try_move_player(1, 1, &mut gs.ecs)
+            }
 
-            VirtualKeyCode::Numpad1 | VirtualKeyCode::B => try_move_player(-1, 1, &mut gs.ecs),
+            VirtualKeyCode::Numpad1 | VirtualKeyCode::B => {
+                return try_move_player(-1, 1, &mut gs.ecs)
+            }
 
             _ => return RunState::AwaitingInput,
         },
@@ -95,36 +166,222 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
     RunState::PlayerTurn
 }
 
-fn get_item(ecs: &mut World) {
+// A locked-up player loses their turns until the lockup wears off.
+pub fn locked_up(ecs: &mut World) -> bool {
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut disabled = ecs.write_storage::<Disable>();
+    let turns = match disabled.get_mut(player_entity) {
+        None => return false,
+        Some(disable) => {
+            disable.turns -= 1;
+            disable.turns
+        }
+    };
+    if turns < 1 {
+        disabled.remove(player_entity);
+    }
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    gamelog
+        .entries
+        .push("Your systems are locked up.".to_string());
+    true
+}
+
+pub fn try_next_level(ecs: &mut World) -> bool {
     let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+    if map.tiles[player_idx] == TileType::DownStairs {
+        true
+    } else {
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        gamelog
+            .entries
+            .push("There is no way down from here.".to_string());
+        false
+    }
+}
+
+// Opens targeting for the equipped gun, if there's one with rounds left.
+fn fire_weapon(ecs: &mut World) -> RunState {
     let player_entity = ecs.fetch::<Entity>();
     let entities = ecs.entities();
-    let items = ecs.read_storage::<Item>();
-    let positions = ecs.read_storage::<Position>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let guns = ecs.read_storage::<Gun>();
+    let names = ecs.read_storage::<Name>();
     let mut gamelog = ecs.fetch_mut::<GameLog>();
 
-    let mut target_item: Option<Entity> = None;
-    for (item_entity, _item, position) in (&entities, &items, &positions).join() {
-        if position.x == player_pos.x && position.y == player_pos.y {
-            target_item = Some(item_entity);
+    let weapon = equipped_weapon(&entities, &equipped, *player_entity);
+    match weapon.and_then(|weapon| guns.get(weapon).map(|gun| (weapon, gun))) {
+        None => {
+            gamelog
+                .entries
+                .push("You have no gun equipped.".to_string());
+            RunState::AwaitingInput
+        }
+        Some((weapon, gun)) if gun.ammo <= 0 => {
+            gamelog.entries.push(format!(
+                "The {} is empty, reload with r.",
+                names.get(weapon).unwrap().name
+            ));
+            RunState::AwaitingInput
+        }
+        Some((weapon, gun)) => RunState::ShowTargeting {
+            range: gun.range,
+            item: weapon,
+        },
+    }
+}
+
+// Reloading takes a turn, so it's only queued when it would actually do something.
+fn reload_weapon(ecs: &mut World) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    {
+        let entities = ecs.entities();
+        let equipped = ecs.read_storage::<Equipped>();
+        let guns = ecs.read_storage::<Gun>();
+        let ammo = ecs.read_storage::<Ammo>();
+        let backpack = ecs.read_storage::<InBackpack>();
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+        let weapon = equipped_weapon(&entities, &equipped, player_entity);
+        let gun = match weapon.and_then(|weapon| guns.get(weapon)) {
+            None => {
+                gamelog
+                    .entries
+                    .push("You have no gun equipped.".to_string());
+                return RunState::AwaitingInput;
+            }
+            Some(gun) => gun,
+        };
+        if gun.ammo >= gun.max_ammo {
+            gamelog
+                .entries
+                .push("The magazine is already full.".to_string());
+            return RunState::AwaitingInput;
+        }
+        let has_rounds = (&ammo, &backpack)
+            .join()
+            .any(|(rounds, pack)| pack.owner == player_entity && rounds.rounds > 0);
+        if !has_rounds {
+            gamelog
+                .entries
+                .push("You have no rounds to reload with.".to_string());
+            return RunState::AwaitingInput;
+        }
+    }
+
+    let mut reload = ecs.write_storage::<WantsToReload>();
+    reload
+        .insert(player_entity, WantsToReload {})
+        .expect("Unable to insert intent");
+    RunState::PlayerTurn
+}
+
+// Triggers the ability bound to a number key. Targeted abilities go through ShowTargeting, area
+// ones are centred on the player and the rest just happen.
+fn use_ability(ecs: &mut World, hotkey: i32) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let player_pos = *ecs.fetch::<Point>();
+    let ability_entity;
+    let mut target = None;
+    {
+        let entities = ecs.entities();
+        let abilities = ecs.read_storage::<Ability>();
+        let energy = ecs.read_storage::<Energy>();
+        let names = ecs.read_storage::<Name>();
+        let ranged = ecs.read_storage::<Ranged>();
+        let aoe = ecs.read_storage::<AreaOfEffect>();
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+        let found = (&entities, &abilities)
+            .join()
+            .find(|(_, ability)| ability.owner == player_entity && ability.hotkey == hotkey);
+        let (entity, ability) = match found {
+            None => return RunState::AwaitingInput,
+            Some(found) => found,
+        };
+        let name = &names.get(entity).unwrap().name;
+        if ability.cooldown_remaining > 0 {
+            gamelog.entries.push(format!(
+                "{} is recharging ({} turns).",
+                name, ability.cooldown_remaining
+            ));
+            return RunState::AwaitingInput;
+        }
+        let available = energy.get(player_entity).map_or(0, |e| e.energy);
+        if available < ability.energy_cost {
+            gamelog.entries.push(format!(
+                "Not enough energy for {} ({}/{}).",
+                name, available, ability.energy_cost
+            ));
+            return RunState::AwaitingInput;
+        }
+
+        if let Some(range) = ranged.get(entity) {
+            return RunState::ShowTargeting {
+                range: range.range,
+                item: entity,
+            };
         }
+        if aoe.get(entity).is_some() {
+            target = Some(player_pos);
+        }
+        ability_entity = entity;
     }
 
-    match target_item {
-        None => gamelog
+    let mut intent = ecs.write_storage::<WantsToUseItem>();
+    intent
+        .insert(
+            player_entity,
+            WantsToUseItem {
+                item: ability_entity,
+                target,
+            },
+        )
+        .expect("Unable to insert intent");
+    RunState::PlayerTurn
+}
+
+// Picks up the only item underfoot straight away. Returns true if there are several to choose from.
+fn get_item(ecs: &mut World) -> bool {
+    let items = items_underfoot(ecs);
+    match items.len() {
+        0 => ecs
+            .fetch_mut::<GameLog>()
             .entries
             .push("There is nothing to pickup!".to_string()),
-        Some(item) => {
-            let mut pickup = ecs.write_storage::<WantsToPickupItem>();
-            pickup
-                .insert(
-                    *player_entity,
-                    WantsToPickupItem {
-                        collected_by: *player_entity,
-                        item,
-                    },
-                )
-                .expect("Unable to pick up...");
-        }
+        1 => pick_up(ecs, &items),
+        _ => return true,
+    }
+    false
+}
+
+pub fn items_underfoot(ecs: &World) -> Vec<Entity> {
+    let player_pos = ecs.fetch::<Point>();
+    let entities = ecs.entities();
+    let items = ecs.read_storage::<Item>();
+    let positions = ecs.read_storage::<Position>();
+    (&entities, &items, &positions)
+        .join()
+        .filter(|(_, _, pos)| pos.x == player_pos.x && pos.y == player_pos.y)
+        .map(|(entity, _, _)| entity)
+        .collect()
+}
+
+// Each intent goes on the item itself, so several can be picked up in one turn.
+pub fn pick_up(ecs: &mut World, items: &[Entity]) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut pickup = ecs.write_storage::<WantsToPickupItem>();
+    for item in items.iter() {
+        pickup
+            .insert(
+                *item,
+                WantsToPickupItem {
+                    collected_by: player_entity,
+                    item: *item,
+                },
+            )
+            .expect("Unable to pick up...");
     }
 }
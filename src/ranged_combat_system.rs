@@ -0,0 +1,241 @@
+use super::{
+    ability_system::OVERCLOCK_BONUS,
+    encumbrance_system::effective_evasion,
+    gamelog::GameLog,
+    particle_system::{damage_color, ParticleBuilder, HIT_MS},
+    Ammo, Cloaked, CombatStats, Encumbered, EquipmentSlot, Equipped, Gun, InBackpack, Map, Name,
+    Overclocked, Position, SufferDamage, WantsToReload, WantsToShoot,
+};
+use rltk::{Point, RandomNumberGenerator, RGB};
+use specs::prelude::*;
+
+// Same to-hit rules as melee: d20 + accuracy has to reach this plus the target's evasion.
+const BASE_TARGET: i32 = 10;
+
+// Whatever is in an entity's weapon slot, if anything.
+pub fn equipped_weapon(
+    entities: &Entities,
+    equipped: &ReadStorage<Equipped>,
+    owner: Entity,
+) -> Option<Entity> {
+    (entities, equipped)
+        .join()
+        .find(|(_, equipped)| equipped.owner == owner && equipped.slot == EquipmentSlot::Weapon)
+        .map(|(entity, _)| entity)
+}
+
+pub struct ShootingSystem {}
+
+impl<'a> System<'a> for ShootingSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        ReadExpect<'a, Map>,
+        WriteStorage<'a, WantsToShoot>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Equipped>,
+        WriteStorage<'a, Gun>,
+        WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, Position>,
+        WriteExpect<'a, ParticleBuilder>,
+        WriteStorage<'a, Cloaked>,
+        ReadStorage<'a, Overclocked>,
+        ReadStorage<'a, Encumbered>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut log,
+            mut rng,
+            map,
+            mut wants_shoot,
+            names,
+            combat_stats,
+            equipped,
+            mut guns,
+            mut inflict_damage,
+            positions,
+            mut particle_builder,
+            mut cloaked,
+            overclocked,
+            encumbered,
+        ) = data;
+
+        for (entity, wants_shoot, name, stats) in
+            (&entities, &wants_shoot, &names, &combat_stats).join()
+        {
+            if stats.hp <= 0 {
+                continue;
+            }
+            let gun_entity = match equipped_weapon(&entities, &equipped, entity) {
+                None => continue,
+                Some(gun_entity) => gun_entity,
+            };
+            let gun = match guns.get_mut(gun_entity) {
+                None => continue,
+                Some(gun) => gun,
+            };
+            if gun.ammo <= 0 {
+                log.entries.push(format!(
+                    "The {} clicks empty.",
+                    names.get(gun_entity).unwrap().name
+                ));
+                continue;
+            }
+            gun.ammo -= 1;
+            // gunfire gives away a cloak
+            cloaked.remove(entity);
+            let bonus = if overclocked.get(entity).is_some() {
+                OVERCLOCK_BONUS
+            } else {
+                0
+            };
+            // the round stops at the first thing in its way
+            let mut impact = wants_shoot.target;
+            if let Some(pos) = positions.get(entity) {
+                let from = Point::new(pos.x, pos.y);
+                impact = map.projectile_impact(from, wants_shoot.target);
+                particle_builder.beam(
+                    from,
+                    impact,
+                    damage_color(gun.damage_type),
+                    rltk::to_cp437('∙'),
+                );
+            }
+
+            let idx = map.xy_idx(impact.x, impact.y);
+            let target = map.tile_content[idx]
+                .iter()
+                .find(|target| combat_stats.get(**target).is_some());
+            let target = match target {
+                None => {
+                    log.entries
+                        .push(format!("{} shoots at nothing.", &name.name));
+                    continue;
+                }
+                Some(target) => *target,
+            };
+            let target_stats = combat_stats.get(target).unwrap();
+            let target_name = names.get(target).unwrap();
+
+            let natural_roll = rng.roll_dice(1, 20);
+            let critical = natural_roll == 20;
+
+            if natural_roll == 1
+                || (!critical
+                    && natural_roll + stats.accuracy + bonus
+                        < BASE_TARGET + effective_evasion(target_stats, encumbered.get(target)))
+            {
+                log.entries.push(format!(
+                    "{} shoots at {} and misses.",
+                    &name.name, &target_name.name
+                ));
+                continue;
+            }
+
+            let mut roll = rng.roll(gun.damage);
+            if critical {
+                roll += rng.roll(gun.damage);
+            }
+            // a gun doesn't care how hard you punch, only armor counts
+            let damage = i32::max(1, roll + bonus - target_stats.defense);
+
+            if critical {
+                log.entries.push(format!(
+                    "{} lands a critical shot on {}, for {} hp!",
+                    &name.name, &target_name.name, damage
+                ));
+            } else {
+                log.entries.push(format!(
+                    "{} shoots {}, for {} hp.",
+                    &name.name, &target_name.name, damage
+                ));
+            }
+            particle_builder.request(
+                impact.x,
+                impact.y,
+                RGB::named(rltk::ORANGE),
+                RGB::named(rltk::BLACK),
+                rltk::to_cp437('‼'),
+                HIT_MS,
+            );
+            SufferDamage::new_damage(&mut inflict_damage, target, damage, gun.damage_type);
+        }
+
+        wants_shoot.clear();
+    }
+}
+
+// Moves loose rounds from the backpack into the equipped gun's magazine.
+pub struct ReloadSystem {}
+
+impl<'a> System<'a> for ReloadSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        Entities<'a>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, WantsToReload>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Equipped>,
+        ReadStorage<'a, InBackpack>,
+        WriteStorage<'a, Gun>,
+        WriteStorage<'a, Ammo>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            entities,
+            mut log,
+            mut wants_reload,
+            names,
+            equipped,
+            backpack,
+            mut guns,
+            mut ammo,
+        ) = data;
+
+        for (entity, _reload) in (&entities, &wants_reload).join() {
+            let gun_entity = match equipped_weapon(&entities, &equipped, entity) {
+                None => continue,
+                Some(gun_entity) => gun_entity,
+            };
+            let gun = match guns.get_mut(gun_entity) {
+                None => continue,
+                Some(gun) => gun,
+            };
+
+            let mut emptied: Vec<Entity> = Vec::new();
+            for (ammo_entity, rounds, pack) in (&entities, &mut ammo, &backpack).join() {
+                if pack.owner != entity || gun.ammo >= gun.max_ammo {
+                    continue;
+                }
+                let loaded = i32::min(rounds.rounds, gun.max_ammo - gun.ammo);
+                gun.ammo += loaded;
+                rounds.rounds -= loaded;
+                if rounds.rounds <= 0 {
+                    emptied.push(ammo_entity);
+                }
+            }
+            for ammo_entity in emptied {
+                entities.delete(ammo_entity).expect("Delete failed");
+            }
+
+            if entity == *player_entity {
+                log.entries.push(format!(
+                    "You reload the {} ({}/{}).",
+                    names.get(gun_entity).unwrap().name,
+                    gun.ammo,
+                    gun.max_ammo
+                ));
+            }
+        }
+
+        wants_reload.clear();
+    }
+}
@@ -29,9 +29,16 @@ pub fn save_game(_ecs: &mut World) {}
 pub fn save_game(ecs: &mut World) {
     // Create helper
     let mapcopy = ecs.get_mut::<super::map::Map>().unwrap().clone();
+    let knowledge = ecs
+        .get_mut::<super::identification_system::ItemKnowledge>()
+        .unwrap()
+        .clone();
     let savehelper = ecs
         .create_entity()
-        .with(SerializationHelper { map: mapcopy })
+        .with(SerializationHelper {
+            map: mapcopy,
+            knowledge,
+        })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
 
@@ -52,23 +59,64 @@ pub fn save_game(ecs: &mut World) {
             Renderable,
             Player,
             Viewshed,
+            Resistances,
+            LightSource,
             Monster,
             Name,
             BlocksTile,
             CombatStats,
+            Shield,
+            ShieldBonus,
+            Energy,
+            Ability,
+            Dash,
+            ProvidesCloak,
+            ProvidesOverclock,
+            Cloaked,
+            Overclocked,
             SufferDamage,
             WantsToMelee,
             Item,
             Consumeable,
+            Charges,
+            Stackable,
+            Weight,
+            ObfuscatedName,
+            ProvidesIdentification,
+            Malware,
+            ProvidesAntivirus,
+            Credits,
+            Value,
+            LootTable,
+            Vendor,
+            Container,
+            Locked,
+            Keycard,
+            WantsToUnlock,
+            Encumbered,
+            Speed,
+            ProvidesRecharge,
+            Implant,
+            Installed,
+            Humanity,
+            ImplantBonus,
+            GrantsAbility,
             Ranged,
             InflictsDamage,
             AreaOfEffect,
             Disable,
+            Equippable,
+            Equipped,
+            Gun,
+            Ammo,
             ProvidesHealing,
             InBackpack,
             WantsToPickupItem,
             WantsToUseItem,
             WantsToDropItem,
+            WantsToThrowItem,
+            WantsToShoot,
+            WantsToReload,
             SerializationHelper
         );
     }
@@ -126,23 +174,64 @@ pub fn load_game(ecs: &mut World) {
             Renderable,
             Player,
             Viewshed,
+            Resistances,
+            LightSource,
             Monster,
             Name,
             BlocksTile,
             CombatStats,
+            Shield,
+            ShieldBonus,
+            Energy,
+            Ability,
+            Dash,
+            ProvidesCloak,
+            ProvidesOverclock,
+            Cloaked,
+            Overclocked,
             SufferDamage,
             WantsToMelee,
             Item,
             Consumeable,
+            Charges,
+            Stackable,
+            Weight,
+            ObfuscatedName,
+            ProvidesIdentification,
+            Malware,
+            ProvidesAntivirus,
+            Credits,
+            Value,
+            LootTable,
+            Vendor,
+            Container,
+            Locked,
+            Keycard,
+            WantsToUnlock,
+            Encumbered,
+            Speed,
+            ProvidesRecharge,
+            Implant,
+            Installed,
+            Humanity,
+            ImplantBonus,
+            GrantsAbility,
             Ranged,
             InflictsDamage,
             AreaOfEffect,
             Disable,
+            Equippable,
+            Equipped,
+            Gun,
+            Ammo,
             ProvidesHealing,
             InBackpack,
             WantsToPickupItem,
             WantsToUseItem,
             WantsToDropItem,
+            WantsToThrowItem,
+            WantsToShoot,
+            WantsToReload,
             SerializationHelper
         );
     }
@@ -157,6 +246,8 @@ pub fn load_game(ecs: &mut World) {
             let mut worldmap = ecs.write_resource::<super::map::Map>();
             *worldmap = h.map.clone();
             worldmap.tile_content = vec![Vec::new(); super::map::MAPCOUNT];
+            let mut knowledge = ecs.write_resource::<super::identification_system::ItemKnowledge>();
+            *knowledge = h.knowledge.clone();
             deleteme = Some(e);
         }
         for (e, _p, pos) in (&entities, &player, &position).join() {
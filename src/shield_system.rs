@@ -0,0 +1,51 @@
+use super::{Equipped, RunState, Shield, ShieldBonus};
+use specs::prelude::*;
+
+// Capacity and recharge rate of an entity's shield, counting whatever it has equipped.
+pub fn shield_capacity(
+    shield: &Shield,
+    owner: Entity,
+    equipped: &ReadStorage<Equipped>,
+    bonuses: &ReadStorage<ShieldBonus>,
+) -> (i32, i32) {
+    let mut capacity = shield.max_shield;
+    let mut recharge_rate = shield.recharge_rate;
+    for (worn, bonus) in (equipped, bonuses).join() {
+        if worn.owner == owner {
+            capacity += bonus.capacity;
+            recharge_rate += bonus.recharge_rate;
+        }
+    }
+    (capacity, recharge_rate)
+}
+
+pub struct ShieldSystem {}
+
+impl<'a> System<'a> for ShieldSystem {
+    type SystemData = (
+        ReadExpect<'a, RunState>,
+        Entities<'a>,
+        WriteStorage<'a, Shield>,
+        ReadStorage<'a, Equipped>,
+        ReadStorage<'a, ShieldBonus>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (runstate, entities, mut shields, equipped, bonuses) = data;
+
+        // once per full turn, same as monsters acting
+        if *runstate != RunState::MonsterTurn {
+            return;
+        }
+
+        for (entity, shield) in (&entities, &mut shields).join() {
+            let (capacity, recharge_rate) = shield_capacity(shield, entity, &equipped, &bonuses);
+            shield.turns_since_hit += 1;
+            if shield.turns_since_hit > shield.recharge_delay {
+                shield.shield += recharge_rate;
+            }
+            // taking off an emitter can leave more charge than the shield can hold
+            shield.shield = i32::min(shield.shield, capacity);
+        }
+    }
+}
@@ -1,17 +1,25 @@
-
 use super::{
-    AreaOfEffect, BlocksTile, CombatStats, Consumeable, Disable, InflictsDamage, Item, Monster,
-    Name, Player, Position, ProvidesHealing, Ranged, Rect, Renderable, SerializeMe, Viewshed,
-    MAPWIDTH,
+    identification_system::ItemKnowledge, Ability, Ammo, AreaOfEffect, BlocksTile, Charges,
+    CombatStats, Consumeable, Container, Credits, DamageType, Dash, Disable, Energy, EquipmentSlot,
+    Equippable, GrantsAbility, Gun, Humanity, Implant, ImplantBonus, ImplantSlot, InBackpack,
+    InflictsDamage, Item, Keycard, LightSource, Locked, Loot, LootDrop, LootTable, Malware, Map,
+    Monster, Name, ObfuscatedName, Player, Position, ProvidesAntivirus, ProvidesCloak,
+    ProvidesHealing, ProvidesIdentification, ProvidesOverclock, ProvidesRecharge, Ranged, Rect,
+    Renderable, Resistances, SerializeMe, Shield, ShieldBonus, Stackable, TileType, Value, Vendor,
+    Viewshed, Weight,
 };
-use rltk::{RandomNumberGenerator, RGB};
+use rltk::{DiceType, RandomNumberGenerator, RGB};
 use specs::prelude::*;
 use specs::saveload::{MarkedBuilder, SimpleMarker};
+use std::collections::HashSet;
 
 // .marked::<SimpleMarker<SerializeMe>>() ADD TO ANYTHING YOU WANT SERIALIZED!
 
+const VENDOR_STOCK: i32 = 5;
+
 pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
-    ecs.create_entity()
+    let player = ecs
+        .create_entity()
         .with(Position {
             x: player_x,
             y: player_y,
@@ -28,6 +36,11 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
             range: 8,
             dirty: true,
         })
+        // headlamp
+        .with(LightSource {
+            color: RGB::from_f32(0.9, 0.9, 0.8),
+            range: 4,
+        })
         .with(Name {
             name: "Hero".to_string(),
         })
@@ -35,71 +48,181 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
             max_hp: 100,
             hp: 100,
             defense: 2,
-            power: 10,
+            power: 5,
+            accuracy: 2,
+            evasion: 2,
+            damage: DiceType::new(1, 6, 0),
+            damage_type: DamageType::Kinetic,
+        })
+        .with(Shield {
+            max_shield: 10,
+            shield: 10,
+            recharge_delay: 3,
+            recharge_rate: 2,
+            turns_since_hit: 0,
+        })
+        .with(Energy {
+            max_energy: 30,
+            energy: 30,
+            regen_rate: 2,
         })
+        .with(Humanity {
+            max_humanity: 10,
+            humanity: 10,
+        })
+        .with(Credits { amount: 50 })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build()
+        .build();
+    abilities(ecs, player);
+    player
+}
+
+fn ability<'a>(
+    ecs: &'a mut World,
+    owner: Entity,
+    hotkey: i32,
+    name: &str,
+    cost: i32,
+    cooldown: i32,
+) -> EntityBuilder<'a> {
+    ecs.create_entity()
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Ability {
+            owner,
+            hotkey,
+            energy_cost: cost,
+            cooldown,
+            cooldown_remaining: 0,
+        })
+}
+
+// The cyberware every run starts with, bound to 1-4.
+fn abilities(ecs: &mut World, owner: Entity) {
+    ability(ecs, owner, 1, "Dash", 8, 5)
+        .with(Dash {})
+        .with(Ranged { range: 4 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+    ability(ecs, owner, 2, "Cloak", 12, 15)
+        .with(ProvidesCloak { turns: 5 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+    ability(ecs, owner, 3, "Overclock", 10, 12)
+        .with(ProvidesOverclock { turns: 5 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+    ability(ecs, owner, 4, "EMP Pulse", 15, 10)
+        .with(InflictsDamage {
+            damage: 6,
+            damage_type: DamageType::Emp,
+        })
+        .with(AreaOfEffect { radius: 2 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
 }
 
 const MAX_MONSTERS: i32 = 4;
 const MAX_ITEMS: i32 = 2;
 
-pub fn spawn_room(ecs: &mut World, room: &Rect) {
-    let mut monster_spawn_points: Vec<usize> = Vec::new();
+pub fn spawn_room(ecs: &mut World, map: &Map, room: &Rect) {
+    let mut area: Vec<usize> = Vec::new();
+    for y in room.y1 + 1..=room.y2 {
+        for x in room.x1 + 1..=room.x2 {
+            area.push(map.xy_idx(x, y));
+        }
+    }
+    spawn_region(ecs, map, &area);
+}
 
+// Fills any list of tile indices (a room, a Voronoi cell, a cave) with monsters and items.
+// Blocked tiles and tiles that already hold an entity are never picked.
+pub fn spawn_region(ecs: &mut World, map: &Map, area: &[usize]) {
+    let mut monster_spawn_points: Vec<usize> = Vec::new();
     let mut item_spawn_points: Vec<usize> = Vec::new();
 
+    let mut occupied: HashSet<usize> = HashSet::new();
+    {
+        let positions = ecs.read_storage::<Position>();
+        for pos in positions.join() {
+            occupied.insert(map.xy_idx(pos.x, pos.y));
+        }
+    }
+    let mut monster_areas: Vec<usize> = area
+        .iter()
+        .filter(|idx| !map.blocked[**idx] && !occupied.contains(*idx))
+        .copied()
+        .collect();
+    let mut item_areas = monster_areas.clone();
+    let mut light_spawn_points: Vec<usize> = Vec::new();
+    let mut container_spawn_points: Vec<usize> = Vec::new();
 
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
         let num_monsters = rng.roll_dice(1, MAX_MONSTERS + 2) - 3;
         let num_items = rng.roll_dice(1, MAX_ITEMS + 2) - 3;
+        // most areas get a light, a few are left dark
+        let has_light = rng.roll_dice(1, 4) > 1;
 
         for _i in 0..num_monsters {
-            let mut added = false;
-            while !added {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-                let idx = (y * MAPWIDTH) + x;
-                if !monster_spawn_points.contains(&idx) {
-                    monster_spawn_points.push(idx);
-                    added = true;
-                }
+            if monster_areas.is_empty() {
+                break;
             }
+            let idx = monster_areas.remove(rng.range(0, monster_areas.len()));
+            monster_spawn_points.push(idx);
         }
+        // nothing lands under a monster, a container there would hide it from melee
+        item_areas.retain(|idx| !monster_spawn_points.contains(idx));
 
         for _i in 0..num_items {
-            let mut added = false;
-            while !added {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-                let idx = (y * MAPWIDTH) + x;
-                if !item_spawn_points.contains(&idx) {
-                    item_spawn_points.push(idx);
-                    added = true;
-                }
+            if item_areas.is_empty() {
+                break;
             }
+            let idx = item_areas.remove(rng.range(0, item_areas.len()));
+            item_spawn_points.push(idx);
+        }
+
+        // now and then something worth rummaging through
+        if rng.roll_dice(1, 3) == 1 && !item_areas.is_empty() {
+            let idx = item_areas.remove(rng.range(0, item_areas.len()));
+            container_spawn_points.push(idx);
+        }
+
+        // lights hang from the ceiling, so they don't care what's on the floor
+        if has_light && !area.is_empty() {
+            light_spawn_points.push(area[rng.range(0, area.len())]);
         }
     }
 
     // Actually spawn the monsters
     for idx in monster_spawn_points.iter() {
-        let x = *idx % MAPWIDTH;
-        let y = *idx / MAPWIDTH;
-        random_monster(ecs, x as i32, y as i32);
+        let x = *idx as i32 % map.width;
+        let y = *idx as i32 / map.width;
+        random_monster(ecs, x, y);
     }
 
     // Actually spawn the items
     for idx in item_spawn_points.iter() {
-        let x = *idx % MAPWIDTH;
-        let y = *idx / MAPWIDTH;
-        random_item(ecs, x as i32, y as i32);
+        let x = *idx as i32 % map.width;
+        let y = *idx as i32 / map.width;
+        random_item(ecs, x, y);
+    }
+
+    for idx in container_spawn_points.iter() {
+        let x = *idx as i32 % map.width;
+        let y = *idx as i32 / map.width;
+        random_container(ecs, x, y);
+    }
+
+    for idx in light_spawn_points.iter() {
+        let x = *idx as i32 % map.width;
+        let y = *idx as i32 / map.width;
+        random_light(ecs, x, y);
     }
 }
 
 pub fn random_monster(ecs: &mut World, x: i32, y: i32) {
-
     let roll: i32;
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
@@ -108,33 +231,234 @@ pub fn random_monster(ecs: &mut World, x: i32, y: i32) {
     match roll {
         1 => android(ecs, x, y),
         _ => robot(ecs, x, y),
-
     }
 }
 
-fn random_item(ecs: &mut World, x: i32, y: i32) {
+fn random_item(ecs: &mut World, x: i32, y: i32) -> Entity {
     let roll: i32;
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        roll = rng.roll_dice(1, 4);
+        roll = rng.roll_dice(1, 13);
     }
-    match roll {
+    let item = match roll {
         1 => volt_pack(ecs, x, y),
         2 => shockwave(ecs, x, y),
         3 => overload(ecs, x, y),
+        4 => emp(ecs, x, y),
+        5 => slug_pistol(ecs, x, y),
+        6 => slug_rounds(ecs, x, y),
+        7 => barrier_emitter(ecs, x, y),
+        8 => recharge_kit(ecs, x, y),
+        9 => random_implant(ecs, x, y),
+        10 => decrypter(ecs, x, y),
+        11 => antivirus(ecs, x, y),
+        12 => keycard(ecs, x, y),
         _ => buster(ecs, x, y),
+    };
+    maybe_infect(ecs, item);
+    item
+}
+
+// Spawns an item by its real name, for loot tables and recipes. Returns None if there's no such
+// item.
+pub fn named_item(ecs: &mut World, x: i32, y: i32, name: &str) -> Option<Entity> {
+    let item = item_by_name(ecs, x, y, name)?;
+    maybe_infect(ecs, item);
+    Some(item)
+}
+
+// A clean item of the given kind, off the map, for copying a stack onto. Unlike named_item it
+// doesn't roll for infection, so copying a stack spends none of the run's random numbers.
+pub fn item_template(ecs: &mut World, name: &str) -> Option<Entity> {
+    let item = item_by_name(ecs, 0, 0, name)?;
+    ecs.write_storage::<Position>().remove(item);
+    Some(item)
+}
+
+fn item_by_name(ecs: &mut World, x: i32, y: i32, name: &str) -> Option<Entity> {
+    let item = match name {
+        "Volt Pack(HP)" => volt_pack(ecs, x, y),
+        "Super Volt Pack" => super_volt_pack(ecs, x, y),
+        "Keycard" => keycard(ecs, x, y),
+        "Recharge Kit" => recharge_kit(ecs, x, y),
+        "Antivirus" => antivirus(ecs, x, y),
+        "Decrypter" => decrypter(ecs, x, y),
+        "Buster Chip" => buster(ecs, x, y),
+        "Shockwave Chip" => shockwave(ecs, x, y),
+        "EMP Chip" => emp(ecs, x, y),
+        "Overload Chip" => overload(ecs, x, y),
+        "Slug Pistol" => slug_pistol(ecs, x, y),
+        "Slug Rounds" => slug_rounds(ecs, x, y),
+        "Barrier Emitter" => barrier_emitter(ecs, x, y),
+        "Capacitor" => salvage(ecs, x, y, "Capacitor", 0.2, 5),
+        "Circuit Board" => salvage(ecs, x, y, "Circuit Board", 0.3, 8),
+        "Power Cell" => salvage(ecs, x, y, "Power Cell", 0.5, 12),
+        _ => return None,
+    };
+    Some(item)
+}
+
+fn random_light(ecs: &mut World, x: i32, y: i32) {
+    let roll: i32;
+    {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        roll = rng.roll_dice(1, 4);
+    }
+    match roll {
+        1 => neon_sign(ecs, x, y, RGB::named(rltk::HOTPINK)),
+        2 => neon_sign(ecs, x, y, RGB::named(rltk::CYAN)),
+        _ => ceiling_lamp(ecs, x, y),
     }
 }
 
-fn android(ecs: &mut World, x: i32, y: i32) {
+fn ceiling_lamp(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Name {
+            name: "Ceiling Lamp".to_string(),
+        })
+        .with(LightSource {
+            color: RGB::from_f32(1.0, 0.95, 0.8),
+            range: 7,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
 
-    monster(ecs, x, y, rltk::to_cp437('A'), "Android");
+fn neon_sign(ecs: &mut World, x: i32, y: i32, color: RGB) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('☼'),
+            fg: color,
+            bg: RGB::named(rltk::BLACK),
+            render_order: 3,
+        })
+        .with(Name {
+            name: "Neon Sign".to_string(),
+        })
+        .with(LightSource { color, range: 5 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
+
+// quick and hard to pin down, but hits lightly
+fn android(ecs: &mut World, x: i32, y: i32) {
+    let stats = CombatStats {
+        max_hp: 14,
+        hp: 14,
+        defense: 0,
+        power: 2,
+        accuracy: 3,
+        evasion: 3,
+        damage: DiceType::new(1, 4, 0),
+        damage_type: DamageType::Electric,
+    };
+    let resistances = Resistances {
+        resistant: vec![DamageType::Electric],
+        vulnerable: vec![DamageType::Thermal],
+    };
+    monster(
+        ecs,
+        x,
+        y,
+        rltk::to_cp437('A'),
+        "Android",
+        stats,
+        resistances,
+    )
+    .with(LootTable {
+        drops: vec![
+            LootDrop {
+                chance: 60,
+                loot: Loot::Credits { max: 10 },
+            },
+            LootDrop {
+                chance: 30,
+                loot: Loot::Item {
+                    name: "Capacitor".to_string(),
+                },
+            },
+            LootDrop {
+                chance: 15,
+                loot: Loot::Item {
+                    name: "Circuit Board".to_string(),
+                },
+            },
+            LootDrop {
+                chance: 10,
+                loot: Loot::Item {
+                    name: "Volt Pack(HP)".to_string(),
+                },
+            },
+            LootDrop {
+                chance: 10,
+                loot: Loot::Item {
+                    name: "Keycard".to_string(),
+                },
+            },
+        ],
+    })
+    .marked::<SimpleMarker<SerializeMe>>()
+    .build();
 }
+
+// slow and armored, hits hard when it connects
 fn robot(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('R'), "Robot");
+    let stats = CombatStats {
+        max_hp: 20,
+        hp: 20,
+        defense: 2,
+        power: 1,
+        accuracy: 0,
+        evasion: 0,
+        damage: DiceType::new(1, 8, 0),
+        damage_type: DamageType::Kinetic,
+    };
+    let resistances = Resistances {
+        resistant: vec![DamageType::Kinetic],
+        vulnerable: vec![DamageType::Emp],
+    };
+    monster(ecs, x, y, rltk::to_cp437('R'), "Robot", stats, resistances)
+        .with(LootTable {
+            drops: vec![
+                LootDrop {
+                    chance: 75,
+                    loot: Loot::Credits { max: 20 },
+                },
+                LootDrop {
+                    chance: 30,
+                    loot: Loot::Item {
+                        name: "Power Cell".to_string(),
+                    },
+                },
+                LootDrop {
+                    chance: 25,
+                    loot: Loot::Item {
+                        name: "Capacitor".to_string(),
+                    },
+                },
+                LootDrop {
+                    chance: 20,
+                    loot: Loot::Item {
+                        name: "Slug Rounds".to_string(),
+                    },
+                },
+            ],
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
 }
 
-fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: rltk::FontCharType, name: S) {
+fn monster<'a, S: ToString>(
+    ecs: &'a mut World,
+    x: i32,
+    y: i32,
+    glyph: rltk::FontCharType,
+    name: S,
+    stats: CombatStats,
+    resistances: Resistances,
+) -> EntityBuilder<'a> {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
@@ -153,18 +477,12 @@ fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: rltk::FontCharTy
             name: name.to_string(),
         })
         .with(BlocksTile {})
-        .with(CombatStats {
-            max_hp: 16,
-            hp: 16,
-            defense: 1,
-            power: 4,
-        })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .with(stats)
+        .with(resistances)
 }
 
 // HEAL
-fn volt_pack(ecs: &mut World, x: i32, y: i32) {
+fn volt_pack(ecs: &mut World, x: i32, y: i32) -> Entity {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
@@ -177,14 +495,157 @@ fn volt_pack(ecs: &mut World, x: i32, y: i32) {
             name: "Volt Pack(HP)".to_string(),
         })
         .with(Item {})
+        .with(Value { credits: 15 })
+        .with(Weight { weight: 1.0 })
         .with(Consumeable {})
+        .with(Stackable { quantity: 1 })
         .with(ProvidesHealing { heal_amount: 10 })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
+}
+
+// Only made at a workbench, from a volt pack and a power cell.
+fn super_volt_pack(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('±'),
+            fg: RGB::named(rltk::CYAN),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Super Volt Pack".to_string(),
+        })
+        .with(Item {})
+        .with(Value { credits: 35 })
+        .with(Weight { weight: 1.0 })
+        .with(Consumeable {})
+        .with(Stackable { quantity: 1 })
+        .with(ProvidesHealing { heal_amount: 25 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+// Opens one locked container.
+fn keycard(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('-'),
+            fg: RGB::named(rltk::LIGHTGREEN),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Keycard".to_string(),
+        })
+        .with(Item {})
+        .with(Value { credits: 20 })
+        .with(Weight { weight: 0.1 })
+        .with(Stackable { quantity: 1 })
+        .with(Keycard {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+// REFILLS CHARGED DEVICES
+fn recharge_kit(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('+'),
+            fg: RGB::named(rltk::GOLD),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Recharge Kit".to_string(),
+        })
+        .with(Item {})
+        .with(Value { credits: 25 })
+        .with(Weight { weight: 2.0 })
+        .with(Consumeable {})
+        .with(Stackable { quantity: 1 })
+        .with(ProvidesRecharge {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+// Chips go by a code name until they're identified, the same code for every chip of a kind.
+fn encrypted_name(ecs: &mut World, real_name: &str) -> String {
+    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+    let mut knowledge = ecs.write_resource::<ItemKnowledge>();
+    knowledge.code_for(real_name, &mut rng)
+}
+
+// Some chips, guns and implants come with a nasty surprise.
+fn maybe_infect(ecs: &mut World, item: Entity) {
+    let infectable = ecs.read_storage::<ObfuscatedName>().get(item).is_some()
+        || ecs.read_storage::<Equippable>().get(item).is_some()
+        || ecs.read_storage::<Implant>().get(item).is_some();
+    if !infectable {
+        return;
+    }
+    let roll = ecs
+        .write_resource::<RandomNumberGenerator>()
+        .roll_dice(1, 6);
+    if roll == 1 {
+        ecs.write_storage::<Malware>()
+            .insert(item, Malware { revealed: false })
+            .expect("Unable to infect item");
+    }
+}
+
+// CLEANS OUT MALWARE
+fn antivirus(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('+'),
+            fg: RGB::named(rltk::LIGHTBLUE),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Antivirus".to_string(),
+        })
+        .with(Item {})
+        .with(Value { credits: 20 })
+        .with(Weight { weight: 0.5 })
+        .with(Consumeable {})
+        .with(Stackable { quantity: 1 })
+        .with(ProvidesAntivirus {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+// CRACKS EVERY CHIP YOU CARRY
+fn decrypter(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('?'),
+            fg: RGB::named(rltk::LIGHTGREEN),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Decrypter".to_string(),
+        })
+        .with(Item {})
+        .with(Value { credits: 30 })
+        .with(Weight { weight: 0.5 })
+        .with(Consumeable {})
+        .with(Stackable { quantity: 1 })
+        .with(ProvidesIdentification {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
 }
 
 // RANGED ATTACK
-fn buster(ecs: &mut World, x: i32, y: i32) {
+fn buster(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let code = encrypted_name(ecs, "Buster Chip");
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
@@ -196,16 +657,23 @@ fn buster(ecs: &mut World, x: i32, y: i32) {
         .with(Name {
             name: "Buster Chip".to_string(),
         })
+        .with(ObfuscatedName { name: code })
         .with(Item {})
-        .with(Consumeable {})
+        .with(Value { credits: 30 })
+        .with(Weight { weight: 0.5 })
+        .with(Charges { current: 3, max: 3 })
         .with(Ranged { range: 8 })
-        .with(InflictsDamage { damage: 12 })
+        .with(InflictsDamage {
+            damage: 12,
+            damage_type: DamageType::Thermal,
+        })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
 }
 
 // RANGED ATTACK WITH AOE
-fn shockwave(ecs: &mut World, x: i32, y: i32) {
+fn shockwave(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let code = encrypted_name(ecs, "Shockwave Chip");
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
@@ -217,17 +685,55 @@ fn shockwave(ecs: &mut World, x: i32, y: i32) {
         .with(Name {
             name: "Shockwave Chip".to_string(),
         })
+        .with(ObfuscatedName { name: code })
         .with(Item {})
+        .with(Value { credits: 40 })
+        .with(Weight { weight: 0.5 })
         .with(Consumeable {})
+        .with(Charges { current: 2, max: 2 })
         .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 5 })
+        .with(InflictsDamage {
+            damage: 5,
+            damage_type: DamageType::Electric,
+        })
         .with(AreaOfEffect { radius: 3 })
         .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .build()
+}
+
+// AOE THAT FRIES ELECTRONICS
+fn emp(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let code = encrypted_name(ecs, "EMP Chip");
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('¥'),
+            fg: RGB::named(rltk::LIGHT_BLUE),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "EMP Chip".to_string(),
+        })
+        .with(ObfuscatedName { name: code })
+        .with(Item {})
+        .with(Value { credits: 30 })
+        .with(Weight { weight: 0.5 })
+        .with(Consumeable {})
+        .with(Stackable { quantity: 1 })
+        .with(Ranged { range: 6 })
+        .with(InflictsDamage {
+            damage: 6,
+            damage_type: DamageType::Emp,
+        })
+        .with(AreaOfEffect { radius: 2 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
 }
 
 // TEMPORARILY INCAPACITATE ENEMY
-fn overload(ecs: &mut World, x: i32, y: i32) {
+fn overload(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let code = encrypted_name(ecs, "Overload Chip");
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
@@ -239,10 +745,441 @@ fn overload(ecs: &mut World, x: i32, y: i32) {
         .with(Name {
             name: "Overload Chip".to_string(),
         })
+        .with(ObfuscatedName { name: code })
         .with(Item {})
+        .with(Value { credits: 35 })
+        .with(Weight { weight: 0.5 })
         .with(Consumeable {})
+        .with(Stackable { quantity: 1 })
         .with(Ranged { range: 3 })
         .with(Disable { turns: 3 })
         .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+// reusable, but needs rounds
+fn slug_pistol(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('¬'),
+            fg: RGB::named(rltk::SILVER),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Slug Pistol".to_string(),
+        })
+        .with(Item {})
+        .with(Value { credits: 60 })
+        .with(Weight { weight: 3.0 })
+        .with(Equippable {
+            slot: EquipmentSlot::Weapon,
+        })
+        .with(Gun {
+            range: 6,
+            ammo: 6,
+            max_ammo: 6,
+            damage: DiceType::new(1, 8, 0),
+            damage_type: DamageType::Kinetic,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn slug_rounds(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let rounds;
+    {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        rounds = rng.roll_dice(2, 4);
+    }
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('='),
+            fg: RGB::named(rltk::SILVER),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Slug Rounds".to_string(),
+        })
+        .with(Item {})
+        .with(Value { credits: 10 })
+        .with(Weight { weight: 1.0 })
+        .with(Ammo { rounds })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+// worn on the belt, makes the shield bigger and quicker to come back
+fn barrier_emitter(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('Ω'),
+            fg: RGB::named(rltk::DEEPSKYBLUE),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Barrier Emitter".to_string(),
+        })
+        .with(Item {})
+        .with(Value { credits: 80 })
+        .with(Weight { weight: 6.0 })
+        .with(Equippable {
+            slot: EquipmentSlot::Shield,
+        })
+        .with(ShieldBonus {
+            capacity: 10,
+            recharge_rate: 1,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn random_implant(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let roll: i32;
+    {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        roll = rng.roll_dice(1, 5);
+    }
+    match roll {
+        1 => kiroshi_optics(ecs, x, y),
+        2 => gorilla_arms(ecs, x, y),
+        3 => reflex_tuners(ecs, x, y),
+        4 => neural_buffer(ecs, x, y),
+        _ => stim_spine(ecs, x, y),
+    }
+}
+
+fn implant<'a>(
+    ecs: &'a mut World,
+    x: i32,
+    y: i32,
+    name: &str,
+    slot: ImplantSlot,
+    humanity_cost: i32,
+) -> EntityBuilder<'a> {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('§'),
+            fg: RGB::named(rltk::MEDIUMORCHID),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Item {})
+        .with(Value { credits: 100 })
+        .with(Weight { weight: 2.0 })
+        .with(Implant {
+            slot,
+            humanity_cost,
+        })
+}
+
+// see further in the dark
+fn kiroshi_optics(ecs: &mut World, x: i32, y: i32) -> Entity {
+    implant(ecs, x, y, "Kiroshi Optics", ImplantSlot::Optics, 2)
+        .with(ImplantBonus {
+            sight: 3,
+            power: 0,
+            evasion: 0,
+            max_energy: 0,
+            speed: 0,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn gorilla_arms(ecs: &mut World, x: i32, y: i32) -> Entity {
+    implant(ecs, x, y, "Gorilla Arms", ImplantSlot::Arms, 3)
+        .with(ImplantBonus {
+            sight: 0,
+            power: 2,
+            evasion: 0,
+            max_energy: 0,
+            speed: 0,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+// faster reactions, harder to hit
+fn reflex_tuners(ecs: &mut World, x: i32, y: i32) -> Entity {
+    implant(ecs, x, y, "Reflex Tuners", ImplantSlot::Legs, 2)
+        .with(ImplantBonus {
+            sight: 0,
+            power: 0,
+            evasion: 2,
+            max_energy: 0,
+            speed: 1,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn neural_buffer(ecs: &mut World, x: i32, y: i32) -> Entity {
+    implant(ecs, x, y, "Neural Buffer", ImplantSlot::Neural, 2)
+        .with(ImplantBonus {
+            sight: 0,
+            power: 0,
+            evasion: 0,
+            max_energy: 10,
+            speed: 0,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+// a self-heal on the next free number key
+fn stim_spine(ecs: &mut World, x: i32, y: i32) -> Entity {
+    implant(ecs, x, y, "Stim", ImplantSlot::Spine, 3)
+        .with(GrantsAbility {
+            energy_cost: 10,
+            cooldown: 20,
+        })
+        .with(ProvidesHealing { heal_amount: 15 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+// Parts pulled out of wrecked machines. Worth a little to a fixer.
+fn salvage(ecs: &mut World, x: i32, y: i32, name: &str, weight: f32, value: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('%'),
+            fg: RGB::named(rltk::SILVER),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Item {})
+        .with(Value { credits: value })
+        .with(Weight { weight })
+        .with(Stackable { quantity: 1 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+// What a destroyed machine leaves behind.
+pub fn credstick(ecs: &mut World, x: i32, y: i32, amount: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('$'),
+            fg: RGB::named(rltk::GOLD),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Credstick".to_string(),
+        })
+        .with(Item {})
+        .with(Credits { amount })
+        .marked::<SimpleMarker<SerializeMe>>()
         .build();
 }
+
+// One fixer per level, somewhere on an empty floor tile, with a few random items for sale.
+pub fn vendor(ecs: &mut World) {
+    let (x, y) = {
+        let map = ecs.fetch::<Map>();
+        let positions = ecs.read_storage::<Position>();
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let mut spot = None;
+        while spot.is_none() {
+            let x = rng.range(1, map.width - 1);
+            let y = rng.range(1, map.height - 1);
+            let occupied = positions.join().any(|pos| pos.x == x && pos.y == y);
+            if map.tiles[map.xy_idx(x, y)] == TileType::Floor && !occupied {
+                spot = Some((x, y));
+            }
+        }
+        spot.unwrap()
+    };
+
+    let vendor = ecs
+        .create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('&'),
+            fg: RGB::named(rltk::GOLD),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 1,
+        })
+        .with(Name {
+            name: "Fixer".to_string(),
+        })
+        .with(BlocksTile {})
+        .with(Vendor {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+
+    stock(ecs, vendor, x, y, VENDOR_STOCK);
+}
+
+// Fills a vendor or container with random items, by spawning them underneath it and moving them
+// into its backpack.
+fn stock(ecs: &mut World, owner: Entity, x: i32, y: i32, count: i32) {
+    for _ in 0..count {
+        random_item(ecs, x, y);
+    }
+    move_items_into(ecs, owner, x, y);
+}
+
+fn move_items_into(ecs: &mut World, owner: Entity, x: i32, y: i32) {
+    let items: Vec<Entity> = (
+        &ecs.entities(),
+        &ecs.read_storage::<Item>(),
+        &ecs.read_storage::<Position>(),
+    )
+        .join()
+        .filter(|(_, _, pos)| pos.x == x && pos.y == y)
+        .map(|(entity, _, _)| entity)
+        .collect();
+    for item in items {
+        ecs.write_storage::<Position>().remove(item);
+        ecs.write_storage::<InBackpack>()
+            .insert(item, InBackpack { owner })
+            .expect("Unable to stock container");
+    }
+}
+
+pub fn random_container(ecs: &mut World, x: i32, y: i32) {
+    let roll: i32;
+    {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        roll = rng.roll_dice(1, 3);
+    }
+    match roll {
+        1 => locker(ecs, x, y),
+        2 => supply_crate(ecs, x, y),
+        _ => robot_husk(ecs, x, y),
+    }
+}
+
+fn container<'a>(
+    ecs: &'a mut World,
+    x: i32,
+    y: i32,
+    glyph: rltk::FontCharType,
+    fg: RGB,
+    name: &str,
+) -> EntityBuilder<'a> {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph,
+            fg,
+            bg: RGB::named(rltk::BLACK),
+            render_order: 1,
+        })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(BlocksTile {})
+        .with(Container {})
+}
+
+// often locked, but better stocked
+fn locker(ecs: &mut World, x: i32, y: i32) {
+    let locked = ecs
+        .write_resource::<RandomNumberGenerator>()
+        .roll_dice(1, 2)
+        == 1;
+    let mut builder = container(
+        ecs,
+        x,
+        y,
+        rltk::to_cp437('⌂'),
+        RGB::named(rltk::STEELBLUE),
+        "Locker",
+    );
+    if locked {
+        builder = builder.with(Locked { difficulty: 14 });
+    }
+    let locker = builder.marked::<SimpleMarker<SerializeMe>>().build();
+    stock(ecs, locker, x, y, 3);
+}
+
+fn supply_crate(ecs: &mut World, x: i32, y: i32) {
+    let crate_entity = container(
+        ecs,
+        x,
+        y,
+        rltk::to_cp437('■'),
+        RGB::named(rltk::BURLYWOOD),
+        "Supply Crate",
+    )
+    .marked::<SimpleMarker<SerializeMe>>()
+    .build();
+    stock(ecs, crate_entity, x, y, 2);
+}
+
+// what's left of a robot, with a few parts still worth pulling out
+fn robot_husk(ecs: &mut World, x: i32, y: i32) {
+    let husk = container(
+        ecs,
+        x,
+        y,
+        rltk::to_cp437('r'),
+        RGB::named(rltk::DIM_GREY),
+        "Robot Husk",
+    )
+    .marked::<SimpleMarker<SerializeMe>>()
+    .build();
+    let parts = ecs
+        .write_resource::<RandomNumberGenerator>()
+        .roll_dice(1, 3);
+    for _ in 0..parts {
+        let roll = ecs
+            .write_resource::<RandomNumberGenerator>()
+            .roll_dice(1, 3);
+        let part = match roll {
+            1 => "Capacitor",
+            2 => "Circuit Board",
+            _ => "Power Cell",
+        };
+        named_item(ecs, x, y, part);
+    }
+    move_items_into(ecs, husk, x, y);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_world, Monster};
+
+    #[test]
+    fn nothing_spawns_on_top_of_a_monster() {
+        let mut map = Map::new_empty(5, 5);
+        let area: Vec<usize> = (1..4).map(|x| map.xy_idx(x, 2)).collect();
+        for idx in area.iter() {
+            map.tiles[*idx] = TileType::Floor;
+        }
+
+        for seed in 0..200 {
+            let mut ecs = test_world(seed);
+            spawn_region(&mut ecs, &map, &area);
+
+            let positions = ecs.read_storage::<Position>();
+            let monsters = ecs.read_storage::<Monster>();
+            let items = ecs.read_storage::<Item>();
+            let containers = ecs.read_storage::<Container>();
+            for (monster_pos, _) in (&positions, &monsters).join() {
+                let shared = (&positions, items.mask() | containers.mask())
+                    .join()
+                    .any(|(pos, _)| pos.x == monster_pos.x && pos.y == monster_pos.y);
+                assert!(!shared, "seed {} put something under a monster", seed);
+            }
+        }
+    }
+}
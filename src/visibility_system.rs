@@ -25,15 +25,18 @@ impl<'a> System<'a> for VisibilitySystem {
                 viewshed
                     .visible_tiles
                     .retain(|p| p.x > 0 && p.x < map.width - 1 && p.y > 0 && p.y < map.height - 1);
+            }
 
-                // If this is the player, reveal what they can see
-                let _p: Option<&Player> = player.get(ent);
-                if let Some(_p) = _p {
-                    for t in map.visible_tiles.iter_mut() {
-                        *t = false
-                    }
-                    for vis in viewshed.visible_tiles.iter() {
-                        let idx = map.xy_idx(vis.x, vis.y);
+            // If this is the player, reveal what they can see. Lights move, so this is redone
+            // every turn: a tile in range but in the dark stays hidden.
+            let _p: Option<&Player> = player.get(ent);
+            if let Some(_p) = _p {
+                for t in map.visible_tiles.iter_mut() {
+                    *t = false
+                }
+                for vis in viewshed.visible_tiles.iter() {
+                    let idx = map.xy_idx(vis.x, vis.y);
+                    if map.is_lit(idx) {
                         map.revealed_tiles[idx] = true;
                         map.visible_tiles[idx] = true;
                     }